@@ -0,0 +1,72 @@
+use crate::Toc;
+
+/// Builds a Toc programmatically.
+///
+/// # Example
+///
+/// ```
+/// use tocer::TocBuilder;
+/// let toc = TocBuilder::new()
+///     .tag("Interface", "110002")
+///     .tag("Title", "Foo")
+///     .file("Foo.lua")
+///     .build();
+/// assert_eq!(toc.tag_order, vec!["Interface", "Title"]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TocBuilder {
+    toc: Toc,
+}
+
+impl TocBuilder {
+    pub fn new() -> TocBuilder {
+        TocBuilder::default()
+    }
+
+    /// Set a tag as `Toc::set_tag` does. Setting the same key again
+    /// overwrites its value but keeps its original position, as parsing does.
+    pub fn tag(mut self, key: &str, value: &str) -> TocBuilder {
+        self.toc.set_tag(key, value);
+        self
+    }
+
+    /// Append a file entry. Line breaks are removed, as they are from tag
+    /// keys and values.
+    pub fn file(mut self, path: &str) -> TocBuilder {
+        self.toc.push_file(path);
+        self
+    }
+
+    pub fn build(self) -> Toc {
+        self.toc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build() {
+        let toc = TocBuilder::new()
+            .tag("Interface", "11302")
+            .file("a.lua")
+            .tag("Title", "Foo")
+            .tag("Interface", "110002")
+            .file("dir\\b.xml")
+            .build();
+        assert_eq!(toc.tag_order, vec!["Interface", "Title"]);
+        assert_eq!(toc.tags["Interface"], "110002");
+        assert_eq!(toc.files, vec!["a.lua", "dir\\b.xml"]);
+
+        let mut out = Vec::new();
+        toc.to_writer(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "## Interface: 110002\n## Title: Foo\na.lua\ndir\\b.xml\n"
+        );
+
+        let toc = TocBuilder::new().file("a.lua\r\nb.lua").build();
+        assert_eq!(toc.files, vec!["a.luab.lua"]);
+    }
+}
@@ -0,0 +1,20 @@
+//! The collections and prelude items used throughout the crate, which come
+//! from `std` when it's enabled, or otherwise from `alloc` and `hashbrown`.
+
+#[cfg(feature = "std")]
+pub(crate) use std::collections::{BTreeMap, HashMap, HashSet};
+
+#[cfg(not(feature = "std"))]
+pub(crate) use alloc::collections::BTreeMap;
+#[cfg(not(feature = "std"))]
+pub(crate) use hashbrown::{HashMap, HashSet};
+
+/// The parts of the `std` prelude which `alloc` provides.
+#[cfg(not(feature = "std"))]
+pub(crate) mod prelude {
+    pub(crate) use alloc::boxed::Box;
+    pub(crate) use alloc::format;
+    pub(crate) use alloc::string::{String, ToString};
+    pub(crate) use alloc::vec;
+    pub(crate) use alloc::vec::Vec;
+}
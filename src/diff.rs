@@ -0,0 +1,152 @@
+#[cfg(not(feature = "std"))]
+use crate::compat::prelude::*;
+use crate::compat::HashSet;
+use crate::Toc;
+
+/// The differences between two Tocs, as returned by `Toc::diff`. Tags are
+/// compared by key regardless of their order, ignoring case as `Toc::tag`
+/// does, and files by their entry text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TocDiff {
+    /// Tags only in the new Toc, as `(key, value)`.
+    pub added_tags: Vec<(String, String)>,
+    /// Tags only in the old Toc, as `(key, value)`.
+    pub removed_tags: Vec<(String, String)>,
+    /// Tags in both with different values, as `(key, old, new)`.
+    pub changed_tags: Vec<(String, String, String)>,
+    /// Files only in the new Toc.
+    pub added_files: Vec<String>,
+    /// Files only in the old Toc.
+    pub removed_files: Vec<String>,
+}
+
+impl TocDiff {
+    /// Whether the two Tocs had the same tags and files.
+    pub fn is_empty(&self) -> bool {
+        self.added_tags.is_empty()
+            && self.removed_tags.is_empty()
+            && self.changed_tags.is_empty()
+            && self.added_files.is_empty()
+            && self.removed_files.is_empty()
+    }
+}
+
+impl Toc {
+    /// Compare this Toc, as the old version, with `other` as the new version.
+    /// Each list is in the order the entries appear in the Toc they come from,
+    /// with tags which aren't in `tag_order` last, sorted by key.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let old: Toc = "## Version: 1\nCore.lua".parse().unwrap();
+    /// let new: Toc = "## Version: 2\nCore.lua".parse().unwrap();
+    /// let diff = old.diff(&new);
+    /// assert_eq!(diff.changed_tags, vec![("Version".into(), "1".into(), "2".into())]);
+    /// ```
+    pub fn diff(&self, other: &Toc) -> TocDiff {
+        let mut diff = TocDiff::default();
+
+        for (key, old) in self.ordered_tags() {
+            match other.tag(key) {
+                None => diff.removed_tags.push((key.to_string(), old.to_string())),
+                Some(new) if new != old => {
+                    diff.changed_tags
+                        .push((key.to_string(), old.to_string(), new.to_string()))
+                }
+                Some(_) => {}
+            }
+        }
+
+        for (key, new) in other.ordered_tags() {
+            if self.tag(key).is_none() {
+                diff.added_tags.push((key.to_string(), new.to_string()));
+            }
+        }
+
+        let old_files: HashSet<&str> = self.files.iter().map(String::as_str).collect();
+        let new_files: HashSet<&str> = other.files.iter().map(String::as_str).collect();
+        diff.removed_files = self
+            .files
+            .iter()
+            .filter(|file| !new_files.contains(file.as_str()))
+            .cloned()
+            .collect();
+        diff.added_files = other
+            .files
+            .iter()
+            .filter(|file| !old_files.contains(file.as_str()))
+            .cloned()
+            .collect();
+
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let old: Toc = "## Interface: 11302\n## Title: Foo\nCore.lua\nOld.lua\n"
+            .parse()
+            .unwrap();
+        let new: Toc = "## Title: Foo\n## Interface: 110002\nCore.lua\nNew.lua\n"
+            .parse()
+            .unwrap();
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff,
+            TocDiff {
+                added_tags: vec![],
+                removed_tags: vec![],
+                changed_tags: vec![(
+                    "Interface".to_string(),
+                    "11302".to_string(),
+                    "110002".to_string()
+                )],
+                added_files: vec!["New.lua".to_string()],
+                removed_files: vec!["Old.lua".to_string()],
+            }
+        );
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn test_diff_tags() {
+        let old: Toc = "## A: 1\n## B: 2\n".parse().unwrap();
+        let new: Toc = "## B: 2\n## C: 3\n".parse().unwrap();
+        let diff = old.diff(&new);
+        assert_eq!(diff.removed_tags, vec![("A".to_string(), "1".to_string())]);
+        assert_eq!(diff.added_tags, vec![("C".to_string(), "3".to_string())]);
+        assert!(diff.changed_tags.is_empty());
+        assert!(old.diff(&old).is_empty());
+
+        let mut new = old.clone();
+        new.tags.insert("D".to_string(), "4".to_string());
+        assert_eq!(
+            old.diff(&new).added_tags,
+            vec![("D".to_string(), "4".to_string())]
+        );
+        assert_eq!(
+            new.diff(&old).removed_tags,
+            vec![("D".to_string(), "4".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_diff_tags_ignore_case() {
+        let old: Toc = "## Title: Foo\n## Version: 1\n".parse().unwrap();
+        let new: Toc = "## title: Foo\n## VERSION: 2\n".parse().unwrap();
+        let diff = old.diff(&new);
+        assert!(diff.added_tags.is_empty());
+        assert!(diff.removed_tags.is_empty());
+        assert_eq!(
+            diff.changed_tags,
+            vec![("Version".to_string(), "1".to_string(), "2".to_string())]
+        );
+    }
+}
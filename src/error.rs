@@ -0,0 +1,128 @@
+use core::fmt;
+
+#[cfg(not(feature = "std"))]
+use crate::compat::prelude::*;
+
+/// The ways in which reading a Toc can fail.
+#[derive(Debug)]
+pub enum TocError {
+    /// The underlying reader failed. `line` is the 1-based number of the last
+    /// line read before the failure, or `None` if no lines were read. Only
+    /// available with the `std` feature.
+    #[cfg(feature = "std")]
+    Io {
+        line: Option<usize>,
+        source: std::io::Error,
+    },
+    /// A line could not be parsed, e.g. because it isn't valid UTF-8. `line`
+    /// is 1-based and `text` is the line's text, with invalid UTF-8 replaced,
+    /// or the start of it if the line is too long.
+    Parse {
+        line: usize,
+        reason: String,
+        text: String,
+    },
+}
+
+impl fmt::Display for TocError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            TocError::Io {
+                line: Some(line),
+                source,
+            } => write!(f, "I/O error after line {}: {}", line, source),
+            #[cfg(feature = "std")]
+            TocError::Io { line: None, source } => write!(f, "I/O error: {}", source),
+            TocError::Parse { line, reason, text } => {
+                write!(f, "line {}: {}: {:?}", line, reason, text)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TocError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TocError::Io { source, .. } => Some(source),
+            TocError::Parse { .. } => None,
+        }
+    }
+}
+
+/// A problem with a line which didn't stop it from being parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Warning {
+    /// The 1-based line number.
+    pub line: usize,
+    /// What is wrong with the line.
+    pub reason: String,
+    /// The line's text.
+    pub text: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}: {:?}", self.line, self.reason, self.text)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for TocError {
+    fn from(e: std::io::Error) -> Self {
+        TocError::Io {
+            line: None,
+            source: e,
+        }
+    }
+}
+
+/// For functions which return `std::io::Result`: I/O errors are unwrapped,
+/// and parse errors become `ErrorKind::InvalidData` wrapping the `TocError`.
+#[cfg(feature = "std")]
+impl From<TocError> for std::io::Error {
+    fn from(e: TocError) -> Self {
+        match e {
+            TocError::Io { source, .. } => source,
+            e => std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let e = TocError::Parse {
+            line: 7,
+            reason: "tag line missing ':'".to_string(),
+            text: "## bad comment".to_string(),
+        };
+        assert_eq!(
+            e.to_string(),
+            "line 7: tag line missing ':': \"## bad comment\""
+        );
+
+        let e = TocError::Io {
+            line: Some(12),
+            source: std::io::Error::other("reset"),
+        };
+        assert_eq!(e.to_string(), "I/O error after line 12: reset");
+        let e = TocError::from(std::io::Error::other("reset"));
+        assert_eq!(e.to_string(), "I/O error: reset");
+
+        let w = Warning {
+            line: 7,
+            reason: "tag line missing ':'".to_string(),
+            text: "## bad comment".to_string(),
+        };
+        assert_eq!(
+            w.to_string(),
+            "line 7: tag line missing ':': \"## bad comment\""
+        );
+    }
+}
@@ -0,0 +1,116 @@
+#[cfg(feature = "std")]
+use core::ops::ControlFlow;
+#[cfg(feature = "std")]
+use std::io::BufReader;
+
+#[cfg(feature = "std")]
+use crate::parse::Lines;
+use crate::Warning;
+#[cfg(feature = "std")]
+use crate::{ParseOptions, Toc, TocError, TocLine};
+
+/// Something found while parsing, as passed to the callback of
+/// `Toc::parse_with`. `line` is the 1-based line number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TocEvent<'a> {
+    Tag {
+        key: &'a str,
+        value: &'a str,
+        line: usize,
+    },
+    File {
+        path: &'a str,
+        line: usize,
+    },
+    Comment {
+        text: &'a str,
+        line: usize,
+    },
+    /// A problem with a line, after the event for the line itself if it has
+    /// one.
+    Warning(&'a Warning),
+}
+
+#[cfg(feature = "std")]
+impl Toc {
+    /// Parse the reader as `from_reader` does, passing each tag, file,
+    /// comment and warning to `callback` as it's found instead of building a
+    /// Toc. Parsing stops early if `callback` returns `ControlFlow::Break`.
+    /// Blank lines and directives aren't reported.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::ops::ControlFlow;
+    /// use tocer::{Toc, TocEvent};
+    /// let reader = std::io::Cursor::new("## Title: Foo\na.lua\nb.lua");
+    /// let mut first_file = None;
+    /// Toc::parse_with(reader, |event| match event {
+    ///     TocEvent::File { path, .. } => {
+    ///         first_file = Some(path.to_string());
+    ///         ControlFlow::Break(())
+    ///     }
+    ///     _ => ControlFlow::Continue(()),
+    /// })
+    /// .unwrap();
+    /// assert_eq!(first_file.as_deref(), Some("a.lua"));
+    /// ```
+    pub fn parse_with<F>(reader: impl std::io::Read, mut callback: F) -> Result<(), TocError>
+    where
+        F: FnMut(TocEvent) -> ControlFlow<()>,
+    {
+        for parsed in Lines::new(BufReader::new(reader), ParseOptions::default()) {
+            let parsed = parsed?;
+            let line = parsed.number;
+            let event = match &parsed.line {
+                TocLine::Tag { key, value, .. } => Some(TocEvent::Tag { key, value, line }),
+                TocLine::File { path, .. } => Some(TocEvent::File { path, line }),
+                TocLine::Comment(text) => Some(TocEvent::Comment { text, line }),
+                TocLine::Directive { .. } | TocLine::MalformedTag(_) | TocLine::Blank => None,
+            };
+
+            let events = event
+                .into_iter()
+                .chain(parsed.warnings.iter().map(TocEvent::Warning));
+            for event in events {
+                if callback(event).is_break() {
+                    return Ok(());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_with() {
+        let s = "## Title: Foo\n# comment\n## bad\n\n#@retail@\na.lua\n";
+        let mut events = Vec::new();
+        Toc::parse_with(std::io::Cursor::new(s), |event| {
+            events.push(format!("{:?}", event));
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        assert_eq!(events.len(), 4);
+        assert!(events[0].starts_with("Tag { key: \"Title\""));
+        assert!(events[1].starts_with("Comment"));
+        assert!(events[2].starts_with("Warning"));
+        assert!(events[3].starts_with("File { path: \"a.lua\", line: 6"));
+    }
+
+    #[test]
+    fn test_parse_with_break() {
+        let mut count = 0;
+        Toc::parse_with(std::io::Cursor::new("a.lua\nb.lua\n"), |_| {
+            count += 1;
+            ControlFlow::Break(())
+        })
+        .unwrap();
+        assert_eq!(count, 1);
+    }
+}
@@ -0,0 +1,50 @@
+#[cfg(feature = "std")]
+use std::path::Path;
+
+/// The type of a toc's file entry, from its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FileKind {
+    /// A Lua script, `.lua`.
+    Lua,
+    /// A UI layout, `.xml`.
+    Xml,
+    /// Another toc, `.toc`.
+    Toc,
+    /// Anything else, including entries without an extension.
+    Other,
+}
+
+impl FileKind {
+    /// Classify a path by its extension, ignoring ASCII case.
+    #[cfg(feature = "std")]
+    pub fn from_path(path: &Path) -> FileKind {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .map_or(FileKind::Other, FileKind::from_extension)
+    }
+
+    /// Classify an extension, without the `.`, ignoring ASCII case.
+    pub(crate) fn from_extension(ext: &str) -> FileKind {
+        match ext.to_ascii_lowercase().as_str() {
+            "lua" => FileKind::Lua,
+            "xml" => FileKind::Xml,
+            "toc" => FileKind::Toc,
+            _ => FileKind::Other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(FileKind::from_path(Path::new("a.lua")), FileKind::Lua);
+        assert_eq!(FileKind::from_path(Path::new("dir/B.XML")), FileKind::Xml);
+        assert_eq!(FileKind::from_path(Path::new("c.Toc")), FileKind::Toc);
+        assert_eq!(FileKind::from_path(Path::new("d.blp")), FileKind::Other);
+        assert_eq!(FileKind::from_path(Path::new("lua")), FileKind::Other);
+    }
+}
@@ -0,0 +1,99 @@
+#[cfg(feature = "std")]
+use core::ops::RangeInclusive;
+
+/// A game client targeted by a toc file's name suffix, e.g. `Foo_Vanilla.toc`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Flavor {
+    /// The retail client, `_Mainline`.
+    Mainline,
+    /// Whichever Classic progression client is current, `_Classic`.
+    Classic,
+    /// Classic Era, `_Vanilla`.
+    Vanilla,
+    /// Burning Crusade Classic, `_TBC` or `_BCC`.
+    TBC,
+    /// Wrath of the Lich King Classic, `_Wrath` or `_WOTLKC`.
+    Wrath,
+    /// Cataclysm Classic, `_Cata`.
+    Cata,
+    /// Mists of Pandaria Classic, `_Mists`.
+    Mists,
+}
+
+impl Flavor {
+    /// Parse a file name suffix such as `Vanilla`, ignoring ASCII case.
+    pub fn from_suffix(suffix: &str) -> Option<Flavor> {
+        let flavor = match suffix.to_ascii_lowercase().as_str() {
+            "mainline" => Flavor::Mainline,
+            "classic" => Flavor::Classic,
+            "vanilla" => Flavor::Vanilla,
+            "tbc" | "bcc" => Flavor::TBC,
+            "wrath" | "wotlkc" => Flavor::Wrath,
+            "cata" => Flavor::Cata,
+            "mists" => Flavor::Mists,
+            _ => return None,
+        };
+        Some(flavor)
+    }
+
+    /// The `Interface` major versions of the clients this flavor covers.
+    #[cfg(feature = "std")]
+    pub(crate) fn majors(self) -> RangeInclusive<u16> {
+        match self {
+            Flavor::Mainline => 9..=u16::MAX,
+            Flavor::Classic => 1..=5,
+            Flavor::Vanilla => 1..=1,
+            Flavor::TBC => 2..=2,
+            Flavor::Wrath => 3..=3,
+            Flavor::Cata => 4..=4,
+            Flavor::Mists => 5..=5,
+        }
+    }
+
+    /// Split a toc file stem such as `Foo_Vanilla` into the addon name and
+    /// its flavor, if it has a recognized `_` or `-` separated suffix.
+    #[cfg(feature = "std")]
+    pub(crate) fn split_stem(stem: &str) -> (&str, Option<Flavor>) {
+        stem.rfind(['_', '-'])
+            .and_then(|i| Some((&stem[..i], Some(Flavor::from_suffix(&stem[i + 1..])?))))
+            .unwrap_or((stem, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_suffix() {
+        assert_eq!(Flavor::from_suffix("Mainline"), Some(Flavor::Mainline));
+        assert_eq!(Flavor::from_suffix("vanilla"), Some(Flavor::Vanilla));
+        assert_eq!(Flavor::from_suffix("BCC"), Some(Flavor::TBC));
+        assert_eq!(Flavor::from_suffix("WOTLKC"), Some(Flavor::Wrath));
+        assert_eq!(Flavor::from_suffix("Sets"), None);
+    }
+
+    #[test]
+    fn test_majors() {
+        assert!(Flavor::Mainline.majors().contains(&11));
+        assert!(!Flavor::Mainline.majors().contains(&1));
+        assert!(Flavor::Classic.majors().contains(&4));
+        assert_eq!(Flavor::Vanilla.majors(), 1..=1);
+    }
+
+    #[test]
+    fn test_split_stem() {
+        assert_eq!(Flavor::split_stem("Foo"), ("Foo", None));
+        assert_eq!(Flavor::split_stem("Foo_Cata"), ("Foo", Some(Flavor::Cata)));
+        assert_eq!(
+            Flavor::split_stem("Foo-Classic"),
+            ("Foo", Some(Flavor::Classic))
+        );
+        assert_eq!(
+            Flavor::split_stem("Foo_Bar_Wrath"),
+            ("Foo_Bar", Some(Flavor::Wrath))
+        );
+        assert_eq!(Flavor::split_stem("Foo_Bar"), ("Foo_Bar", None));
+    }
+}
@@ -1,56 +1,259 @@
-use std::collections::HashMap;
-use std::io::{BufRead, BufReader};
+//! Parses World of Warcraft addon `.toc` files.
+//!
+//! The `std` feature, which is on by default, is needed for reading from
+//! `std::io` readers and for anything to do with paths. Without it, the
+//! `alloc` feature builds the rest of the crate, including `Toc::parse_bytes`,
+//! for `no_std` targets with an allocator, using `hashbrown`'s `HashMap` for
+//! `Toc::tags`.
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "alloc")))]
+compile_error!("tocer needs either the `std` or the `alloc` feature");
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+
+use core::ops::Range;
+use core::str::FromStr;
+#[cfg(feature = "std")]
+use std::fs::File;
+#[cfg(feature = "std")]
+use std::io::{BufReader, Write};
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+
+#[cfg(not(feature = "std"))]
+use compat::prelude::*;
+use compat::{BTreeMap, HashMap, HashSet};
+#[cfg(feature = "std")]
+use parse::Lines;
+use parse::ParsedLine;
+
+mod builder;
+mod compat;
+mod diff;
+mod error;
+mod event;
+mod file_kind;
+mod flavor;
+mod markup;
+mod options;
+pub mod parse;
+mod version;
+
+pub use builder::TocBuilder;
+pub use diff::TocDiff;
+pub use error::{TocError, Warning};
+pub use event::TocEvent;
+pub use file_kind::FileKind;
+pub use flavor::Flavor;
+pub use markup::strip_color_codes;
+pub use options::ParseOptions;
+pub use version::InterfaceVersion;
+
+/// The locale codes the client uses for localized tags such as `Title-deDE`.
+pub const LOCALES: &[&str] = &[
+    "enUS", "enGB", "deDE", "esES", "esMX", "frFR", "itIT", "koKR", "ptBR", "ruRU", "zhCN", "zhTW",
+];
+
+/// A single line of a toc file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TocLine {
+    /// A `## Key: Value` line, with the line's original text.
+    Tag {
+        key: String,
+        value: String,
+        text: String,
+    },
+    /// A file path, with the line's original text.
+    File { path: String, text: String },
+    /// A packager directive such as `#@no-lib-strip@`, with the text between
+    /// the `#@` and `@` as `name`.
+    Directive { name: String, text: String },
+    /// A line starting with `##` which isn't a valid tag, e.g. because it's
+    /// missing the `:`.
+    MalformedTag(String),
+    /// A line starting with `#` which isn't a tag or directive.
+    Comment(String),
+    /// A line which is empty or only contains whitespace.
+    Blank,
+}
+
+/// A line of a toc file and where it came from. Lines are equal if
+/// everything but their `span` is, so the same toc with different line
+/// endings compares equal.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceLine {
+    /// The 1-based line number, or `None` if the line was added after
+    /// parsing.
+    pub number: Option<usize>,
+    pub content: TocLine,
+    /// The innermost packager block the line is in, e.g. `no-lib-strip` for
+    /// lines between `#@no-lib-strip@` and `#@end-no-lib-strip@`, or `None`
+    /// if it's unconditional. The markers are in their enclosing block.
+    pub condition: Option<String>,
+    /// The range of bytes the line's text covers in the input, excluding its
+    /// line terminator and any byte order mark, or `None` if the line was
+    /// added after parsing. Transcoded input is measured in UTF-8.
+    pub span: Option<Range<usize>>,
+}
+
+impl PartialEq for SourceLine {
+    fn eq(&self, other: &Self) -> bool {
+        self.number == other.number
+            && self.content == other.content
+            && self.condition == other.condition
+    }
+}
+
+impl Eq for SourceLine {}
+
+/// A parsed toc file. Two Tocs are equal if they have the same tags and the
+/// same files in the same order. The order of the tags and everything else,
+/// such as comments, warnings and where the toc was read from, is ignored.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Toc {
     pub tags: HashMap<String, String>,
+    /// The keys of `tags` in the order they first appeared.
+    pub tag_order: Vec<String>,
     pub files: Vec<String>,
+    /// Problems found while parsing, such as lines which were ignored because
+    /// they couldn't be parsed.
+    pub warnings: Vec<Warning>,
+    /// Every line of the source in order, which `tags` and `files` are views
+    /// of.
+    pub lines: Vec<SourceLine>,
+    /// The 1-based line numbers and keys of tags which overwrote an earlier
+    /// tag with the same key.
+    pub duplicate_tags: Vec<(usize, String)>,
+    /// The names of packager directives such as `#@no-lib-strip@`, in order.
+    pub directives: Vec<String>,
+    /// The directory containing the toc file, if it was read by `from_path`.
+    /// Only available with the `std` feature.
+    #[cfg(feature = "std")]
+    pub dir: Option<PathBuf>,
+    /// The addon's name inferred from the toc's file name, if it was read by
+    /// `from_path`.
+    pub name: Option<String>,
 }
 
-fn key_value_pair_begin(input: &str) -> Result<&str, &str> {
-    let mut chars = input.chars();
-    if chars.next() == Some('#') && chars.next() == Some('#') {
-        Ok(&input[2..])
-    } else {
-        Err(input)
+impl PartialEq for Toc {
+    fn eq(&self, other: &Self) -> bool {
+        self.tags == other.tags && self.files == other.files
     }
 }
 
-fn key(input: &str) -> Result<(&str, &str), &str> {
-    for (i, ch) in input.chars().enumerate() {
-        if ch == ':' {
-            let key = input[..i].trim();
-            return Ok((key, &input[i..]));
+impl Eq for Toc {}
+
+/// Split a comma separated tag value into its trimmed, non-empty entries.
+/// An escaped `\,` is kept as a comma within the entry.
+fn split_list(value: &str) -> Vec<String> {
+    let mut entries = vec![String::new()];
+    let mut chars = value.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if chars.peek() == Some(&',') => {
+                chars.next();
+                entries.last_mut().unwrap().push(',');
+            }
+            ',' => entries.push(String::new()),
+            _ => entries.last_mut().unwrap().push(ch),
         }
     }
 
-    Err(input)
+    entries
+        .iter()
+        .map(|entry| entry.trim())
+        .filter(|entry| !entry.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Remove `\r` and `\n` from text which will be written as part of a single
+/// line, so that it can't start another one.
+fn strip_line_breaks(text: &str) -> String {
+    text.replace(['\r', '\n'], "")
 }
 
-fn value(input: &str) -> Result<&str, &str> {
-    if input.chars().next() != Some(':') {
-        Err(input)
-    } else {
-        Ok(&input[1..].trim())
+/// The directories and file name of a file entry, which may use `\` or `/`
+/// as the separator.
+fn entry_components(entry: &str) -> impl Iterator<Item = &str> {
+    entry
+        .split(['\\', '/'])
+        .filter(|component| !component.is_empty())
+}
+
+/// The extension of a file entry's file name, as `Path::extension` finds it.
+fn entry_extension(entry: &str) -> Option<&str> {
+    let name = entry_components(entry).last()?;
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => Some(ext),
+        _ => None,
     }
 }
 
-fn key_value_pair(input: &str) -> Result<(&str, &str), &str> {
-    let (k, input) = key(key_value_pair_begin(input)?)?;
-    let v = value(input)?;
-    Ok((k, v))
+/// Convert a file entry, which uses `\` as the separator, to a native path.
+#[cfg(feature = "std")]
+fn normalize_path(entry: &str) -> PathBuf {
+    entry_components(entry).collect()
+}
+
+/// Whether at least three quarters of the byte pairs in `head` are an ASCII
+/// character followed by a zero, as in UTF-16LE without a byte order mark.
+#[cfg(feature = "encoding")]
+fn looks_like_utf16le(head: &[u8]) -> bool {
+    let pairs = head.chunks_exact(2);
+    let total = pairs.len();
+    let ascii = pairs.filter(|pair| pair[0] != 0 && pair[1] == 0).count();
+    total > 0 && ascii * 4 >= total * 3
 }
 
-fn file_path(input: &str) -> Result<&str, &str> {
-    if input.chars().next() == Some('#') {
-        Err(input)
-    } else {
-        Ok(input.trim())
+/// Decodes a reader to UTF-8 one buffer at a time, so that the parser's
+/// `max_line_bytes` limit applies before the whole input is in memory.
+#[cfg(feature = "encoding")]
+struct Transcoder<R> {
+    reader: R,
+    decoder: encoding_rs::Decoder,
+    /// The decoded text, of which `pos` bytes have been read.
+    out: String,
+    pos: usize,
+    done: bool,
+}
+
+#[cfg(feature = "encoding")]
+impl<R: std::io::BufRead> std::io::Read for Transcoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.pos == self.out.len() && !self.done {
+            let input = self.reader.fill_buf()?;
+            let last = input.is_empty();
+            self.out.clear();
+            self.pos = 0;
+            if let Some(len) = self.decoder.max_utf8_buffer_length(input.len()) {
+                self.out.reserve(len);
+            }
+            let (_, read, _) = self.decoder.decode_to_string(input, &mut self.out, last);
+            self.reader.consume(read);
+            self.done = last;
+        }
+
+        let out = &self.out.as_bytes()[self.pos..];
+        let n = out.len().min(buf.len());
+        buf[..n].copy_from_slice(&out[..n]);
+        self.pos += n;
+        Ok(n)
     }
 }
 
 impl Toc {
-    /// Create a Toc object from the reader. Duplicate tag keys are silently
-    /// overwritten.
+    /// Create a Toc object from the reader. Duplicate tag keys, compared
+    /// ignoring case as the client does, overwrite the earlier value but keep
+    /// the casing and position of their first occurrence, and are recorded in
+    /// `duplicate_tags`. A UTF-8 byte order mark at the start of the input is
+    /// skipped.
     ///
     /// # Example
     ///
@@ -58,112 +261,2937 @@ impl Toc {
     /// use tocer::Toc;
     /// let reader = std::io::Cursor::new("## Interface: 1");
     /// let toc = Toc::from_reader(reader).unwrap();
-    /// dbg!(&toc.tags["Interface"]);
+    /// dbg!(&toc);
     /// ```
-    pub fn from_reader(reader: impl std::io::Read) -> std::io::Result<Toc> {
-        let mut buf = BufReader::new(reader);
-        let mut line = String::new();
-        let mut tags = HashMap::new();
-        let mut files = Vec::new();
+    #[cfg(feature = "std")]
+    pub fn from_reader(reader: impl std::io::Read) -> Result<Toc, TocError> {
+        Toc::from_reader_with_options(reader, ParseOptions::default())
+    }
 
-        while buf.read_line(&mut line)? != 0 {
-            if let Ok((k, v)) = key_value_pair(&line) {
-                tags.insert(k.to_string(), v.to_string());
-            } else if let Ok(path) = file_path(&line) {
-                println!("Adding {}", path);
-                files.push(path.to_string());
+    /// Create a Toc object from the reader as `from_reader` does, but with
+    /// the parsing behaviour described by `options`.
+    #[cfg(feature = "std")]
+    pub fn from_reader_with_options(
+        reader: impl std::io::Read,
+        options: ParseOptions,
+    ) -> Result<Toc, TocError> {
+        let mut toc = Toc::default();
+        for parsed in Lines::new(BufReader::new(reader), options) {
+            toc.push_parsed(parsed?);
+        }
+        Ok(toc)
+    }
+
+    /// Create a Toc object from an in-memory buffer as `from_reader` does,
+    /// splitting it into lines directly instead of going through
+    /// `std::io::Read`. Unlike `from_reader`, this is also available without
+    /// the `std` feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let toc = Toc::parse_bytes(b"## Interface: 1\nFoo.lua").unwrap();
+    /// assert_eq!(toc.files, vec!["Foo.lua"]);
+    /// ```
+    pub fn parse_bytes(bytes: &[u8]) -> Result<Toc, TocError> {
+        let mut parser = parse::Parser::new(ParseOptions::default());
+        let mut toc = Toc::default();
+        for line in bytes.split_inclusive(|&b| b == b'\n') {
+            toc.push_parsed(parser.parse_bytes(line)?);
+        }
+        Ok(toc)
+    }
+
+    /// Parse a stream of several toc documents separated by `---` lines, as
+    /// `from_reader_multi_with_separator` does.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let reader = std::io::Cursor::new("## Title: A\n---\n## Title: B\n");
+    /// let tocs = Toc::from_reader_multi(reader).unwrap();
+    /// assert_eq!(tocs[1].title(), Some("B"));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn from_reader_multi(reader: impl std::io::Read) -> std::io::Result<Vec<Toc>> {
+        Toc::from_reader_multi_with_separator(reader, "---")
+    }
+
+    /// Parse a stream of several toc documents, each parsed as `from_reader`
+    /// does, separated by lines which are `separator` apart from surrounding
+    /// whitespace. Line numbers start from 1 again in each document, and
+    /// `SourceLine::span` is relative to the start of the document. A
+    /// separator at the end of the stream doesn't start another document, and
+    /// an empty stream has none. Parse errors are returned as
+    /// `ErrorKind::InvalidData` wrapping the `TocError`.
+    #[cfg(feature = "std")]
+    pub fn from_reader_multi_with_separator(
+        reader: impl std::io::Read,
+        separator: &str,
+    ) -> std::io::Result<Vec<Toc>> {
+        use std::io::{BufRead, Read};
+
+        let mut reader = BufReader::new(reader);
+        let mut tocs = Vec::new();
+        let mut parser = parse::Parser::new(ParseOptions::default());
+        let mut toc = None;
+        let mut line = Vec::new();
+        while (&mut reader)
+            .take(parser.read_limit())
+            .read_until(b'\n', &mut line)?
+            != 0
+        {
+            if String::from_utf8_lossy(&line).trim() == separator {
+                tocs.push(toc.take().unwrap_or_default());
+                parser = parse::Parser::new(ParseOptions::default());
+            } else {
+                let parsed = parser.parse_bytes(&line)?;
+                toc.get_or_insert_with(Toc::default).push_parsed(parsed);
             }
             line.clear();
         }
 
-        Ok(Toc { tags, files })
+        tocs.extend(toc);
+        Ok(tocs)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Create a Toc object from the reader as `from_reader_with_options` does,
+    /// but transcode the input to UTF-8 first if it starts with a UTF-16 byte
+    /// order mark, or looks like UTF-16LE and `options.detect_utf16` is set.
+    /// Invalid UTF-16 is replaced with U+FFFD. Other input is parsed
+    /// directly.
+    #[cfg(feature = "encoding")]
+    pub fn from_reader_with_encoding(
+        reader: impl std::io::Read,
+        options: ParseOptions,
+    ) -> Result<Toc, TocError> {
+        use std::io::BufRead;
 
-    #[test]
-    fn test_key_begin() {
-        assert_eq!(key_value_pair_begin(""), Err(""));
-        assert_eq!(key_value_pair_begin("#"), Err("#"));
-        assert_eq!(key_value_pair_begin("##"), Ok(""));
+        let mut buf = BufReader::new(reader);
+        let head = buf.fill_buf()?;
+        let encoding = match encoding_rs::Encoding::for_bom(head) {
+            Some((encoding, _)) if encoding != encoding_rs::UTF_8 => encoding,
+            None if options.detect_utf16 && looks_like_utf16le(head) => encoding_rs::UTF_16LE,
+            _ => return Toc::from_reader_with_options(buf, options),
+        };
+
+        let reader = Transcoder {
+            reader: buf,
+            decoder: encoding.new_decoder_with_bom_removal(),
+            out: String::new(),
+            pos: 0,
+            done: false,
+        };
+        Toc::from_reader_with_options(reader, options)
     }
 
-    #[test]
-    fn test_key() {
-        assert_eq!(key(""), Err(""));
-        assert_eq!(key("a"), Err("a"));
-        assert_eq!(key(" a "), Err(" a "));
-        assert_eq!(key("a:"), Ok(("a", ":")));
-        assert_eq!(key(" a:"), Ok(("a", ":")));
-        assert_eq!(key("a:"), Ok(("a", ":")));
-        assert_eq!(key("a :"), Ok(("a", ":")));
-        assert_eq!(key(" a :"), Ok(("a", ":")));
+    /// Create a Toc object from an asynchronous reader, as `from_reader` does.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// use tocer::Toc;
+    /// let toc = Toc::from_async_reader(&b"## Interface: 1"[..]).await.unwrap();
+    /// dbg!(&toc.tags["Interface"]);
+    /// # });
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub async fn from_async_reader(
+        reader: impl tokio::io::AsyncRead + Unpin,
+    ) -> Result<Toc, TocError> {
+        use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+
+        let mut buf = tokio::io::BufReader::new(reader);
+        let mut line = Vec::new();
+        let mut parser = parse::Parser::new(ParseOptions::default());
+        let mut toc = Toc::default();
+
+        let limit = parser.read_limit();
+        while (&mut buf)
+            .take(limit)
+            .read_until(b'\n', &mut line)
+            .await
+            .map_err(|e| parser.io_error(e))?
+            != 0
+        {
+            toc.push_parsed(parser.parse_bytes(&line)?);
+            line.clear();
+        }
+
+        Ok(toc)
     }
 
-    #[test]
-    fn test_value() {
-        assert_eq!(value(""), Err(""));
-        assert_eq!(value(" "), Err(" "));
-        assert_eq!(value(": "), Ok(""));
-        assert_eq!(value(":A"), Ok("A"));
-        assert_eq!(value(": A"), Ok("A"));
-        assert_eq!(value(": A "), Ok("A"));
+    /// Lazily parse the reader one line at a time, without building a Toc.
+    /// Iteration stops after the first error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::{Toc, TocLine};
+    /// let reader = std::io::Cursor::new("## Interface: 1\nCore.lua");
+    /// let interface = Toc::parse_lines(reader).find_map(|line| match line {
+    ///     Ok(TocLine::Tag { key, value, .. }) if key == "Interface" => Some(value),
+    ///     _ => None,
+    /// });
+    /// assert_eq!(interface.as_deref(), Some("1"));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn parse_lines(
+        reader: impl std::io::Read,
+    ) -> impl Iterator<Item = Result<TocLine, TocError>> {
+        Lines::new(BufReader::new(reader), ParseOptions::default())
+            .map(|parsed| parsed.map(|parsed| parsed.line))
     }
 
-    #[test]
-    fn test_empty_reader() {
-        let reader = std::io::Cursor::new("");
-        let toc = Toc::from_reader(reader).unwrap();
-        assert!(toc.tags.is_empty());
-        assert!(toc.files.is_empty());
+    /// Add a parsed line to the Toc, updating the views of it.
+    fn push_parsed(&mut self, parsed: ParsedLine) {
+        match &parsed.line {
+            TocLine::Tag { key, value, .. } => {
+                let key = self.tag_key(key).unwrap_or(key).to_string();
+                if self.tags.insert(key.clone(), value.clone()).is_none() {
+                    self.tag_order.push(key);
+                } else {
+                    self.duplicate_tags.push((parsed.number, key));
+                }
+            }
+            TocLine::File { path, .. } => self.files.push(path.clone()),
+            TocLine::Directive { name, .. } => self.directives.push(name.clone()),
+            TocLine::MalformedTag(_) | TocLine::Comment(_) | TocLine::Blank => {}
+        }
+
+        self.warnings.extend(parsed.warnings);
+        self.lines.push(SourceLine {
+            number: Some(parsed.number),
+            content: parsed.line,
+            condition: parsed.condition,
+            span: Some(parsed.span),
+        });
     }
 
-    #[test]
-    fn test_files() {
-        let s = concat!(
-            "a.lua\n",
-            "b.lua\n",
-            "# comment\n",
-            "c.lua  \n",
-            "## bad comment\n",
-            "dir\\d.xml\n",
-            " f"
-        );
-        let reader = std::io::Cursor::new(s);
-        let toc = Toc::from_reader(reader).unwrap();
-        assert!(toc.tags.is_empty());
-        assert_eq!(toc.files.len(), 5);
-        assert_eq!(toc.files[0], "a.lua");
-        assert_eq!(toc.files[1], "b.lua");
-        assert_eq!(toc.files[2], "c.lua");
-        assert_eq!(toc.files[3], "dir\\d.xml");
-        assert_eq!(toc.files[4], "f");
+    /// Create a Toc object from the file at `path`. Errors from opening the
+    /// file, such as it not existing, are returned unchanged as
+    /// `TocError::Io`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use tocer::Toc;
+    /// let toc = Toc::from_path("Interface/AddOns/Bagnon/Bagnon.toc").unwrap();
+    /// dbg!(&toc.tags["Title"]);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Toc, TocError> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let mut toc = Toc::from_reader(BufReader::new(file))?;
+        toc.dir = path.parent().map(Path::to_path_buf);
+        toc.name = Toc::addon_name_from_path(path);
+        Ok(toc)
     }
 
-    #[test]
-    fn test_tags() {
-        let s = concat!(
-            "##Interface:11302\n",
-            "##Title: |cff20ff20Bagnon|r\n",
-            "## Author: Tuller & Jaliborc (João Cardoso)\n",
-            "## d : 8.2.16\n",
-            "# comment\n",
-            "## e : Bagnon_Sets \n",
-            "## bad comment\n",
-            "##   \t OptionalDeps : BagBrother, WoWUnit\n"
-        );
-        let reader = std::io::Cursor::new(s);
-        let toc = Toc::from_reader(reader).unwrap();
-        assert_eq!(toc.tags.len(), 6);
-        assert_eq!(toc.tags["Interface"], "11302");
-        assert_eq!(toc.tags["Title"], "|cff20ff20Bagnon|r");
-        assert_eq!(toc.tags["Author"], "Tuller & Jaliborc (João Cardoso)");
-        assert_eq!(toc.tags["d"], "8.2.16");
-        assert_eq!(toc.tags["e"], "Bagnon_Sets");
-        assert_eq!(toc.tags["OptionalDeps"], "BagBrother, WoWUnit");
-        assert!(toc.files.is_empty());
+    /// Parse every toc for the addon in `dir`, such as `Foo/Foo.toc` and
+    /// `Foo/Foo_Vanilla.toc`, keyed by the flavor from the file name. Files
+    /// without a suffix are `Flavor::Mainline`, unless there's also an
+    /// explicit `_Mainline` toc, which takes precedence as it does in the
+    /// client. Two suffixed tocs for the same flavor, e.g. `Foo_TBC.toc` and
+    /// `Foo_BCC.toc`, are an `ErrorKind::InvalidData` error. Tocs for other
+    /// addons, whose names don't match the folder's, are ignored. Parse
+    /// errors are returned as `ErrorKind::InvalidData` wrapping the
+    /// `TocError`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::path::Path;
+    /// use tocer::{Flavor, Toc};
+    /// let tocs = Toc::from_addon_dir(Path::new("Interface/AddOns/Bagnon")).unwrap();
+    /// dbg!(&tocs[&Flavor::Mainline]);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn from_addon_dir(dir: &Path) -> std::io::Result<HashMap<Flavor, Toc>> {
+        let folder = dir.file_name().and_then(|name| name.to_str());
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let is_toc = path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("toc"));
+            let name = Toc::addon_name_from_path(&path);
+            if is_toc && (folder.is_none() || name.as_deref() == folder) {
+                paths.push(path);
+            }
+        }
+        // Sorted so that errors don't depend on the directory's order.
+        paths.sort();
+
+        let mut tocs = HashMap::new();
+        let mut suffixed: HashMap<Flavor, PathBuf> = HashMap::new();
+        for path in paths {
+            let toc = Toc::from_path(&path)?;
+            match Toc::flavor_from_path(&path) {
+                Some(flavor) => {
+                    if let Some(other) = suffixed.insert(flavor, path.clone()) {
+                        let message = format!(
+                            "{} and {} are both for {:?}",
+                            other.display(),
+                            path.display(),
+                            flavor
+                        );
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            message,
+                        ));
+                    }
+                    tocs.insert(flavor, toc);
+                }
+                None => {
+                    tocs.entry(Flavor::Mainline).or_insert(toc);
+                }
+            }
+        }
+
+        Ok(tocs)
+    }
+
+    /// Create a Toc object from `<addon_name>/<addon_name>.toc` inside a zip
+    /// archive, as addons are distributed, without extracting it. A missing
+    /// toc is returned as a `TocError::Io` with the kind `NotFound`, as are
+    /// other errors from reading the archive.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use tocer::Toc;
+    /// let file = std::fs::File::open("Bagnon-8.2.16.zip").unwrap();
+    /// let toc = Toc::from_zip(file, "Bagnon").unwrap();
+    /// assert_eq!(toc.name.as_deref(), Some("Bagnon"));
+    /// ```
+    #[cfg(feature = "zip")]
+    pub fn from_zip(
+        reader: impl std::io::Read + std::io::Seek,
+        addon_name: &str,
+    ) -> Result<Toc, TocError> {
+        use std::io::{Error, ErrorKind};
+
+        let path = format!("{0}/{0}.toc", addon_name);
+        let mut archive = zip::ZipArchive::new(reader).map_err(Error::from)?;
+        let entry = match archive.by_name(&path) {
+            Ok(entry) => entry,
+            Err(zip::result::ZipError::FileNotFound) => {
+                let message = format!("{} is not in the archive", path);
+                return Err(Error::new(ErrorKind::NotFound, message).into());
+            }
+            Err(e) => return Err(Error::from(e).into()),
+        };
+
+        let mut toc = Toc::from_reader(entry)?;
+        toc.name = Some(addon_name.to_string());
+        Ok(toc)
+    }
+
+    /// Infer the addon's name from a toc's path. The client requires it to
+    /// match the addon's folder name, minus any client suffix such as
+    /// `_Mainline`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::path::Path;
+    /// use tocer::Toc;
+    /// let name = Toc::addon_name_from_path(Path::new("Bagnon/Bagnon_Mainline.toc"));
+    /// assert_eq!(name.as_deref(), Some("Bagnon"));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn addon_name_from_path(path: &Path) -> Option<String> {
+        let stem = path.file_stem()?.to_str()?;
+        Some(Flavor::split_stem(stem).0.to_string())
+    }
+
+    /// The client a toc targets according to its file name suffix, e.g.
+    /// `Foo_Vanilla.toc`. Files without a suffix return `None`, which the
+    /// client treats as `Flavor::Mainline`.
+    #[cfg(feature = "std")]
+    pub fn flavor_from_path(path: &Path) -> Option<Flavor> {
+        Flavor::split_stem(path.file_stem()?.to_str()?).1
+    }
+
+    /// Check that the `Interface` tag suits the client targeted by the suffix
+    /// of the toc's file name at `path`, e.g. that `Foo_Vanilla.toc` isn't for
+    /// retail. Returns a description of the mismatch if none of the listed
+    /// versions suit, or `None` if they do or there's no suffix or version to
+    /// compare.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::path::Path;
+    /// use tocer::Toc;
+    /// let toc: Toc = "## Interface: 110002".parse().unwrap();
+    /// assert!(toc.check_flavor_consistency(Path::new("Foo_Vanilla.toc")).is_some());
+    /// assert!(toc.check_flavor_consistency(Path::new("Foo_Mainline.toc")).is_none());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn check_flavor_consistency(&self, path: &Path) -> Option<String> {
+        let flavor = Toc::flavor_from_path(path)?;
+        let versions = self.interface_versions();
+        let majors = flavor.majors();
+        if versions.is_empty() || versions.iter().any(|v| majors.contains(&v.major)) {
+            return None;
+        }
+
+        Some(format!(
+            "{} is for {:?} but its Interface tag {:?} is for another client",
+            path.file_name()?.to_string_lossy(),
+            flavor,
+            self.tag("Interface")?.trim()
+        ))
+    }
+
+    /// Look up a tag's value, ignoring ASCII case in the key as the WoW client
+    /// does. An exact match is preferred over other casings.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let reader = std::io::Cursor::new("## Interface: 1");
+    /// let toc = Toc::from_reader(reader).unwrap();
+    /// assert_eq!(toc.tag("interface"), Some("1"));
+    /// ```
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tag_key(name)
+            .and_then(|key| self.tags.get(key))
+            .map(String::as_str)
+    }
+
+    /// Look up a tag's value as `tag` does, returning `default` if it doesn't
+    /// exist.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let toc: Toc = "## Title: Foo".parse().unwrap();
+    /// assert_eq!(toc.tag_or("Version", "0.0.0"), "0.0.0");
+    /// ```
+    pub fn tag_or<'a>(&'a self, name: &str, default: &'a str) -> &'a str {
+        self.tag(name).unwrap_or(default)
+    }
+
+    /// The tags as `(key, value)` pairs in the order they first appeared.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let toc: Toc = "## Title: Foo\n## Interface: 1".parse().unwrap();
+    /// let tags: Vec<_> = toc.iter_tags().collect();
+    /// assert_eq!(tags, vec![("Title", "Foo"), ("Interface", "1")]);
+    /// ```
+    pub fn iter_tags(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.tag_order.iter().filter_map(move |key| {
+            self.tags
+                .get_key_value(key)
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+        })
+    }
+
+    /// Whether any tag's value contains `needle`, ignoring case.
+    pub fn tag_contains(&self, needle: &str) -> bool {
+        let needle = needle.to_lowercase();
+        self.tags
+            .values()
+            .any(|value| value.to_lowercase().contains(&needle))
+    }
+
+    /// Take the tags as `(key, value)` pairs in the order they were first
+    /// seen, without copying them. Tags which aren't in `tag_order` come
+    /// last, sorted by key, as they do when written.
+    pub fn into_tags(self) -> Vec<(String, String)> {
+        let mut tags = self.tags;
+        let mut ordered: Vec<_> = self
+            .tag_order
+            .into_iter()
+            .filter_map(|key| tags.remove_entry(&key))
+            .collect();
+        let mut unordered: Vec<_> = tags.into_iter().collect();
+        unordered.sort_unstable();
+        ordered.extend(unordered);
+        ordered
+    }
+
+    /// Take the file entries without copying them.
+    pub fn into_files(self) -> Vec<String> {
+        self.files
+    }
+
+    /// The tags sorted by key, for output which doesn't depend on the order
+    /// they were written in.
+    pub fn tags_sorted(&self) -> BTreeMap<&str, &str> {
+        self.tags
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect()
+    }
+
+    /// The tags in the order `iter_tags` returns them, followed by any which
+    /// were added to `tags` without updating `tag_order`, sorted by key.
+    fn ordered_tags(&self) -> Vec<(&str, &str)> {
+        let mut tags: Vec<_> = self.iter_tags().collect();
+        let ordered: HashSet<&str> = self.tag_order.iter().map(String::as_str).collect();
+        let mut unordered: Vec<_> = self
+            .tags
+            .iter()
+            .filter(|(key, _)| !ordered.contains(key.as_str()))
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        unordered.sort_unstable();
+        tags.extend(unordered);
+        tags
+    }
+
+    /// The number of distinct tags.
+    pub fn tag_count(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// The number of file entries, including any duplicates.
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// The keys of the tags in the order they were first seen.
+    pub fn tag_keys(&self) -> impl Iterator<Item = &str> {
+        self.iter_tags().map(|(key, _)| key)
+    }
+
+    /// The custom `X-` tags, e.g. `X-Website`, keyed without the prefix. The
+    /// prefix is matched ignoring case. These tags are still in `tags` too.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let toc: Toc = "## Title: Foo\n## X-Website: https://example.com".parse().unwrap();
+    /// assert_eq!(toc.custom_tags()["Website"], "https://example.com");
+    /// ```
+    pub fn custom_tags(&self) -> HashMap<&str, &str> {
+        self.iter_tags()
+            .filter(|(k, _)| {
+                k.len() > 2 && k.get(..2).is_some_and(|p| p.eq_ignore_ascii_case("X-"))
+            })
+            .map(|(k, v)| (&k[2..], v))
+            .collect()
+    }
+
+    /// The stored key matching `name` as `tag` looks it up.
+    fn tag_key(&self, name: &str) -> Option<&str> {
+        if let Some((key, _)) = self.tags.get_key_value(name) {
+            return Some(key);
+        }
+
+        self.tag_order
+            .iter()
+            .find(|key| key.eq_ignore_ascii_case(name) && self.tags.contains_key(*key))
+            .map(String::as_str)
+    }
+
+    /// The 1-based line number of the line which set the tag's current value,
+    /// found with the same lookup as `tag`. Returns `None` if the tag doesn't
+    /// exist or wasn't read from the source.
+    pub fn line_of_tag(&self, name: &str) -> Option<usize> {
+        self.tag_line(name)?.number
+    }
+
+    /// The tag's value exactly as written after the `:`, including any
+    /// surrounding whitespace or inline comment, found with the same lookup
+    /// as `tag`. Returns `None` if the tag doesn't exist or `lines` is empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let toc: Toc = "## Version :  8.2.16  ".parse().unwrap();
+    /// assert_eq!(toc.tag("Version"), Some("8.2.16"));
+    /// assert_eq!(toc.raw_tag("Version"), Some("  8.2.16  "));
+    /// ```
+    pub fn raw_tag(&self, name: &str) -> Option<&str> {
+        match &self.tag_line(name)?.content {
+            TocLine::Tag { text, .. } => text.find(':').map(|i| &text[i + 1..]),
+            _ => None,
+        }
+    }
+
+    /// The last line which declares the tag found by `tag_key`.
+    fn tag_line(&self, name: &str) -> Option<&SourceLine> {
+        let key = self.tag_key(name)?;
+        self.lines.iter().rfind(|line| {
+            matches!(&line.content, TocLine::Tag { key: k, .. } if k.eq_ignore_ascii_case(key))
+        })
+    }
+
+    /// Parse the `Interface` tag, returning `None` if it's missing or isn't a
+    /// number. Use `interface_versions` for tags listing several versions.
+    pub fn interface_version(&self) -> Option<InterfaceVersion> {
+        InterfaceVersion::parse(self.tag("Interface")?)
+    }
+
+    /// Parse a comma separated `Interface` tag such as `11302, 110002`, as
+    /// used by files supporting several clients. Entries which aren't numbers
+    /// are skipped.
+    pub fn interface_versions(&self) -> Vec<InterfaceVersion> {
+        self.tag("Interface")
+            .map(|value| {
+                value
+                    .split(',')
+                    .filter_map(InterfaceVersion::parse)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Whether any version in the `Interface` tag has the same major version
+    /// as `client`, which is how the client decides if an addon is out of
+    /// date.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::{InterfaceVersion, Toc};
+    /// let toc: Toc = "## Interface: 11504, 110002".parse().unwrap();
+    /// assert!(toc.is_compatible_with(InterfaceVersion::parse("110005").unwrap()));
+    /// ```
+    pub fn is_compatible_with(&self, client: InterfaceVersion) -> bool {
+        self.interface_versions()
+            .iter()
+            .any(|version| version.major == client.major)
+    }
+
+    /// Check that the tags the client requires are present, returning a
+    /// description of each problem found. `Interface` must be present and list
+    /// at least one valid version, and `Title` must be present. Versions which
+    /// aren't 5 or 6 digits long, such as `123`, are reported as suspicious
+    /// since they're almost certainly typos.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let toc: Toc = "## Title: Foo".parse().unwrap();
+    /// assert_eq!(toc.validate(), Err(vec!["missing Interface tag".to_string()]));
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+
+        match self.tag("Interface") {
+            None => problems.push("missing Interface tag".to_string()),
+            Some(value) if self.interface_versions().is_empty() => {
+                problems.push(format!("invalid Interface tag: {:?}", value))
+            }
+            Some(value) => {
+                for version in value.split(',').map(str::trim) {
+                    let digits = version.len();
+                    if InterfaceVersion::parse(version).is_some() && !(5..=6).contains(&digits) {
+                        problems.push(format!(
+                            "suspicious Interface version {:?}: expected 5 or 6 digits",
+                            version
+                        ));
+                    }
+                }
+            }
+        }
+
+        if self.tag("Title").is_none() {
+            problems.push("missing Title tag".to_string());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// The `Title` tag.
+    pub fn title(&self) -> Option<&str> {
+        self.tag("Title")
+    }
+
+    /// The `Title` tag with its color codes and textures removed, for display
+    /// as plain text.
+    pub fn title_plain(&self) -> Option<String> {
+        self.title().map(strip_color_codes)
+    }
+
+    /// The name to show for the addon: the `Title` tag as `title_plain`
+    /// returns it, or `fallback_name` if there's no title, as the client
+    /// falls back to the folder name.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let toc: Toc = "## Interface: 110002".parse().unwrap();
+    /// assert_eq!(toc.display_title("Bagnon"), "Bagnon");
+    /// ```
+    pub fn display_title(&self, fallback_name: &str) -> String {
+        self.title_plain()
+            .unwrap_or_else(|| fallback_name.to_string())
+    }
+
+    /// The paths of the `|T...|t` texture icons in the `Title` tag, in the
+    /// order they appear. `title_plain` removes them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let toc: Toc = "## Title: |TInterface\\Icons\\Bag:16|t Bagnon".parse().unwrap();
+    /// assert_eq!(toc.title_icons(), vec!["Interface\\Icons\\Bag"]);
+    /// ```
+    pub fn title_icons(&self) -> Vec<String> {
+        self.title().map(markup::texture_paths).unwrap_or_default()
+    }
+
+    /// The `Author` tag.
+    pub fn author(&self) -> Option<&str> {
+        self.tag("Author")
+    }
+
+    /// The `Version` tag.
+    pub fn version(&self) -> Option<&str> {
+        self.tag("Version")
+    }
+
+    /// Parse the `Version` tag as a semantic version, allowing a leading `v`.
+    /// Returns `None` if it's missing or isn't a semantic version.
+    #[cfg(feature = "semver")]
+    pub fn semver(&self) -> Option<semver::Version> {
+        let version = self.version()?.trim();
+        let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+        semver::Version::parse(version).ok()
+    }
+
+    /// The `Notes` tag.
+    pub fn notes(&self) -> Option<&str> {
+        self.tag("Notes")
+    }
+
+    /// The `Notes` tag with its color codes removed by `strip_color_codes`
+    /// and its `|n` escapes turned into newlines.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let toc: Toc = "## Notes: |cffffd200Bags|r|nAll in one".parse().unwrap();
+    /// assert_eq!(toc.notes_plain().unwrap(), "Bags\nAll in one");
+    /// ```
+    pub fn notes_plain(&self) -> Option<String> {
+        self.notes()
+            .map(|notes| strip_color_codes(notes).replace("|n", "\n"))
+    }
+
+    /// Look up the `base` tag for `locale`, e.g. `Title-deDE`, falling back
+    /// to `base` itself if there is no localized version or `locale` isn't
+    /// one of `LOCALES`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let toc: Toc = "## Title: Bags\n## Title-deDE: Taschen".parse().unwrap();
+    /// assert_eq!(toc.localized_tag("Title", "deDE"), Some("Taschen"));
+    /// assert_eq!(toc.localized_tag("Title", "frFR"), Some("Bags"));
+    /// ```
+    pub fn localized_tag(&self, base: &str, locale: &str) -> Option<&str> {
+        if LOCALES.contains(&locale) {
+            if let Some(value) = self.tag(&format!("{}-{}", base, locale)) {
+                return Some(value);
+            }
+        }
+
+        self.tag(base)
+    }
+
+    /// Whether a `0`/`1` tag is set to `1`.
+    fn tag_flag(&self, name: &str) -> bool {
+        self.tag(name).map(str::trim) == Some("1")
+    }
+
+    fn tag_list(&self, name: &str) -> Vec<String> {
+        self.tag(name).map(split_list).unwrap_or_default()
+    }
+
+    /// The addons which must be loaded first, from the `Dependencies` and
+    /// `RequiredDeps` tags.
+    pub fn dependencies(&self) -> Vec<String> {
+        let mut deps = self.tag_list("Dependencies");
+        let mut seen: HashSet<String> = deps.iter().cloned().collect();
+        for dep in self.tag_list("RequiredDeps") {
+            if seen.insert(dep.clone()) {
+                deps.push(dep);
+            }
+        }
+        deps
+    }
+
+    /// The addons which should be loaded first if present, from the
+    /// `OptionalDeps` tag.
+    pub fn optional_dependencies(&self) -> Vec<String> {
+        self.tag_list("OptionalDeps")
+    }
+
+    /// Iterate over the file entries in order. To find the files without
+    /// parsing the whole input first, filter `parse_lines` for
+    /// `TocLine::File` instead.
+    pub fn files_iter(&self) -> impl Iterator<Item = &str> {
+        self.files.iter().map(String::as_str)
+    }
+
+    /// Whether `name` is one of the file entries, ignoring case and whether
+    /// `\\` or `/` separates the directories.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let toc: Toc = "Libs\\Embeds.xml".parse().unwrap();
+    /// assert!(toc.has_file("libs/embeds.xml"));
+    /// ```
+    pub fn has_file(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.files.iter().any(|file| {
+            let file = file.to_lowercase();
+            entry_components(&file).eq(entry_components(&name))
+        })
+    }
+
+    /// The file entries sorted ignoring case and treating `\\` and `/` as the
+    /// same separator, with ties broken by the entries themselves. This is
+    /// for comparing and reporting file lists; the client loads files in the
+    /// order of `files`, which isn't changed.
+    pub fn files_sorted(&self) -> Vec<&str> {
+        let mut files: Vec<&str> = self.files_iter().collect();
+        files.sort_by_cached_key(|file| (file.replace('\\', "/").to_lowercase(), *file));
+        files
+    }
+
+    /// The file entries as native paths, e.g. `dir\d.xml` becomes `dir/d.xml`
+    /// on Unix. The entries in `files` aren't changed.
+    #[cfg(feature = "std")]
+    pub fn files_normalized(&self) -> Vec<PathBuf> {
+        self.files.iter().map(|file| normalize_path(file)).collect()
+    }
+
+    /// The file entries with the extension `ext`, ignoring ASCII case.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let toc: Toc = "a.lua\nb.xml\nc.LUA".parse().unwrap();
+    /// assert_eq!(toc.files_with_extension("lua"), vec!["a.lua", "c.LUA"]);
+    /// ```
+    pub fn files_with_extension(&self, ext: &str) -> Vec<&str> {
+        self.files
+            .iter()
+            .filter(|file| entry_extension(file).is_some_and(|e| e.eq_ignore_ascii_case(ext)))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Each file entry with its type, from its extension as
+    /// `FileKind::from_path` classifies it, treating `\\` as a separator.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::{FileKind, Toc};
+    /// let toc: Toc = "a.lua\nLibs\\Embeds.XML".parse().unwrap();
+    /// assert_eq!(
+    ///     toc.classified_files(),
+    ///     vec![("a.lua", FileKind::Lua), ("Libs\\Embeds.XML", FileKind::Xml)]
+    /// );
+    /// ```
+    pub fn classified_files(&self) -> Vec<(&str, FileKind)> {
+        self.files
+            .iter()
+            .map(|file| {
+                let kind = entry_extension(file).map_or(FileKind::Other, FileKind::from_extension);
+                (file.as_str(), kind)
+            })
+            .collect()
+    }
+
+    /// The file entries which the client won't load, i.e. those which
+    /// `classified_files` doesn't classify as Lua, XML or another toc.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let toc: Toc = "Core.lua\nREADME.txt".parse().unwrap();
+    /// assert_eq!(toc.unsupported_files(), vec!["README.txt"]);
+    /// ```
+    pub fn unsupported_files(&self) -> Vec<&str> {
+        self.classified_files()
+            .into_iter()
+            .filter(|(_, kind)| *kind == FileKind::Other)
+            .map(|(file, _)| file)
+            .collect()
+    }
+
+    /// The file entries as native paths joined onto `base_dir`, which is
+    /// usually the addon's folder.
+    #[cfg(feature = "std")]
+    pub fn resolved_files(&self, base_dir: &Path) -> Vec<PathBuf> {
+        self.files
+            .iter()
+            .map(|file| base_dir.join(normalize_path(file)))
+            .collect()
+    }
+
+    /// The resolved file entries which don't exist under `base_dir`.
+    #[cfg(feature = "std")]
+    pub fn missing_files(&self, base_dir: &Path) -> Vec<PathBuf> {
+        self.resolved_files(base_dir)
+            .into_iter()
+            .filter(|path| !path.exists())
+            .collect()
+    }
+
+    /// Expand `pattern`, written like a file entry such as `Modules\\*.lua`,
+    /// against the files which exist under `base_dir`, so that tooling can
+    /// see what a glob-like entry resolves to. The matches are sorted. An
+    /// invalid pattern matches nothing, and unreadable paths are skipped.
+    #[cfg(feature = "glob")]
+    pub fn files_matching_glob(&self, base_dir: &Path, pattern: &str) -> Vec<PathBuf> {
+        let base = glob::Pattern::escape(&base_dir.to_string_lossy());
+        let pattern = Path::new(&base).join(normalize_path(pattern));
+        match glob::glob(&pattern.to_string_lossy()) {
+            Ok(paths) => paths.filter_map(Result::ok).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// The file entries resolved against `dir`, or `None` if the Toc wasn't
+    /// read by `from_path`.
+    #[cfg(feature = "std")]
+    pub fn resolved_files_in_dir(&self) -> Option<Vec<PathBuf>> {
+        self.dir.as_deref().map(|dir| self.resolved_files(dir))
+    }
+
+    /// Set the value of the tag `key`, found with the same lookup as `tag`,
+    /// keeping `tag_order` and `lines` in sync so that `to_writer` reproduces
+    /// every other line unchanged. The last line declaring the tag is
+    /// rewritten as `## Key: value`, or if the tag is new, a line is added
+    /// after the last tag line outside of a packager block such as
+    /// `#@retail@`, or at the start if there isn't one. Line breaks in `key` and `value` are removed,
+    /// since they would otherwise start a new line when written.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let mut toc: Toc = "## Version: 1.0\n# Files\nCore.lua".parse().unwrap();
+    /// toc.set_tag("version", "1.1");
+    /// let mut out = Vec::new();
+    /// toc.to_writer(&mut out).unwrap();
+    /// assert_eq!(out, b"## Version: 1.1\n# Files\nCore.lua\n");
+    /// ```
+    pub fn set_tag(&mut self, key: &str, value: &str) {
+        let (key, value) = (&strip_line_breaks(key), &strip_line_breaks(value));
+        let key = &self.tag_key(key).unwrap_or(key).to_string();
+        if self
+            .tags
+            .insert(key.to_string(), value.to_string())
+            .is_none()
+        {
+            self.tag_order.push(key.to_string());
+        }
+
+        if self.lines.is_empty() {
+            return;
+        }
+
+        let content = TocLine::Tag {
+            key: key.to_string(),
+            value: value.to_string(),
+            text: format!("## {}: {}", key, value),
+        };
+        let is_tag =
+            |l: &SourceLine| matches!(l.content, TocLine::Tag { .. }) && l.condition.is_none();
+        let existing = self.lines.iter().rposition(
+            |l| matches!(&l.content, TocLine::Tag { key: k, .. } if k.eq_ignore_ascii_case(key)),
+        );
+
+        match existing {
+            Some(i) => self.lines[i].content = content,
+            None => {
+                let i = self.lines.iter().rposition(is_tag).map_or(0, |i| i + 1);
+                self.lines.insert(
+                    i,
+                    SourceLine {
+                        number: None,
+                        content,
+                        condition: None,
+                        span: None,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Append a file entry, keeping `lines` in sync. Line breaks are removed
+    /// from `path` as `set_tag` does.
+    pub(crate) fn push_file(&mut self, path: &str) {
+        let path = &strip_line_breaks(path);
+        self.files.push(path.to_string());
+        if !self.lines.is_empty() {
+            self.lines.push(SourceLine {
+                number: None,
+                content: TocLine::File {
+                    path: path.to_string(),
+                    text: path.to_string(),
+                },
+                condition: None,
+                span: None,
+            });
+        }
+    }
+
+    /// Rename the tag `from` to `to`, found with the same lookup as `tag`,
+    /// keeping its value and position. Returns whether `from` existed. If
+    /// another tag already has the key `to`, it's overwritten and its lines
+    /// are removed. Renamed lines are rewritten as `## to: value`, with line
+    /// breaks removed from `to` as `set_tag` does.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let mut toc: Toc = "## X-Curse-Packaged-Version: 1.0".parse().unwrap();
+    /// assert!(toc.rename_tag("X-Curse-Packaged-Version", "Version"));
+    /// assert_eq!(toc.version(), Some("1.0"));
+    /// ```
+    pub fn rename_tag(&mut self, from: &str, to: &str) -> bool {
+        let to = &strip_line_breaks(to);
+        let from = match self.tag_key(from) {
+            Some(key) => key.to_string(),
+            None => return false,
+        };
+        let is_key = |content: &TocLine, name: &str| matches!(content, TocLine::Tag { key, .. } if key.eq_ignore_ascii_case(name));
+
+        if let Some(existing) = self.tag_key(to).map(str::to_string) {
+            if existing != from {
+                self.tags.remove(&existing);
+                self.tag_order.retain(|key| *key != existing);
+                self.lines.retain(|line| !is_key(&line.content, &existing));
+            }
+        }
+
+        let value = self.tags.remove(&from).unwrap_or_default();
+        self.tags.insert(to.to_string(), value);
+        for key in self.tag_order.iter_mut().filter(|key| **key == from) {
+            *key = to.to_string();
+        }
+
+        for line in &mut self.lines {
+            if let TocLine::Tag { key, value, text } = &mut line.content {
+                if key.eq_ignore_ascii_case(&from) {
+                    *key = to.to_string();
+                    *text = format!("## {}: {}", to, value);
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Remove repeated file entries, keeping the first occurrence of each.
+    /// The client would otherwise load the file more than once.
+    pub fn dedup_files(&mut self) {
+        let mut seen = HashSet::new();
+        self.files.retain(|file| seen.insert(file.clone()));
+
+        let mut seen = HashSet::new();
+        self.lines.retain(|line| match &line.content {
+            TocLine::File { path, .. } => seen.insert(path.clone()),
+            _ => true,
+        });
+    }
+
+    /// Merge `other` into this Toc. Tags from `other` overwrite tags with the
+    /// same key, and are otherwise added after the existing tags. Files from
+    /// `other` are appended unless they're already listed. Everything else in
+    /// `other`, such as comments and warnings, is dropped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let mut toc: Toc = "## Interface: 110002\nCore.lua".parse().unwrap();
+    /// toc.merge("## Interface: 11302\nCore.lua\nClassic.lua".parse().unwrap());
+    /// assert_eq!(toc.tags["Interface"], "11302");
+    /// assert_eq!(toc.files, vec!["Core.lua", "Classic.lua"]);
+    /// ```
+    pub fn merge(&mut self, other: Toc) {
+        for (key, value) in other.ordered_tags() {
+            self.set_tag(key, value);
+        }
+
+        let mut seen: HashSet<String> = self.files.iter().cloned().collect();
+        for file in &other.files {
+            if seen.insert(file.clone()) {
+                self.push_file(file);
+            }
+        }
+    }
+
+    /// Whether `LoadOnDemand` is `1`, meaning the addon is only loaded when
+    /// requested.
+    pub fn load_on_demand(&self) -> bool {
+        self.tag_flag("LoadOnDemand")
+    }
+
+    /// Whether the addon looks like a library rather than something a user
+    /// would enable directly. Any of these signals counts:
+    ///
+    /// - `LoadOnDemand` is `1`.
+    /// - `X-Category` is `Library` or `Libraries`, ignoring case.
+    /// - The addon's name, or its plain title if the name isn't known, starts
+    ///   with `Lib` followed by anything other than a lowercase letter, e.g.
+    ///   `LibStub` or `Lib-1.0` but not `Libra`.
+    pub fn is_library(&self) -> bool {
+        let category = self.tag("X-Category").map(str::trim);
+        let name = match &self.name {
+            Some(name) => Some(name.clone()),
+            None => self.title_plain(),
+        };
+        let lib_prefix = name.as_deref().is_some_and(|name| {
+            name.strip_prefix("Lib")
+                .is_some_and(|rest| !rest.starts_with(|c: char| c.is_ascii_lowercase()))
+        });
+
+        self.load_on_demand()
+            || category.is_some_and(|c| {
+                c.eq_ignore_ascii_case("Library") || c.eq_ignore_ascii_case("Libraries")
+            })
+            || lib_prefix
+    }
+
+    /// The addons whose loading causes this one to load, from `LoadWith`.
+    pub fn load_with(&self) -> Vec<String> {
+        self.tag_list("LoadWith")
+    }
+
+    /// The addons responsible for loading this one, from `LoadManagers`.
+    pub fn load_managers(&self) -> Vec<String> {
+        self.tag_list("LoadManagers")
+    }
+
+    /// Whether `Secure` is `1`, as it is for Blizzard's own addons.
+    pub fn secure(&self) -> bool {
+        self.tag_flag("Secure")
+    }
+
+    /// The game types the addon may load in, from `AllowLoadGameType`.
+    pub fn allowed_game_types(&self) -> Vec<String> {
+        self.tag_list("AllowLoadGameType")
+    }
+
+    /// The global variables saved per account, from `SavedVariables`.
+    pub fn saved_variables(&self) -> Vec<String> {
+        self.tag_list("SavedVariables")
+    }
+
+    /// The global variables saved per character, from
+    /// `SavedVariablesPerCharacter`.
+    pub fn saved_variables_per_character(&self) -> Vec<String> {
+        self.tag_list("SavedVariablesPerCharacter")
+    }
+
+    /// Write the Toc to the writer in `.toc` format by reproducing `lines`,
+    /// including comments and blank lines. Trailing whitespace and line
+    /// endings aren't preserved, every line ends with `\n`.
+    ///
+    /// Changes made directly to `tags` and `files` are reconciled with
+    /// `lines`: a tag line whose value changed is rewritten as
+    /// `## Key: Value`, new tags are written where `set_tag` would add them, new
+    /// files are written before the next file line which is still listed, or
+    /// at the end, and lines for removed tags and files are left out.
+    ///
+    /// If `lines` is empty, e.g. for a Toc built by hand, each tag is written
+    /// as a `## Key: Value` line in the order it was first seen, followed by
+    /// one file path per line.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let reader = std::io::Cursor::new("## Interface: 1\nFoo.lua");
+    /// let toc = Toc::from_reader(reader).unwrap();
+    /// let mut out = Vec::new();
+    /// toc.to_writer(&mut out).unwrap();
+    /// assert_eq!(out, b"## Interface: 1\nFoo.lua\n");
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn to_writer(&self, mut writer: impl Write) -> std::io::Result<()> {
+        if self.lines.is_empty() {
+            return writer.write_all(self.to_canonical_string().as_bytes());
+        }
+
+        // The index of the line which sets each tag's value, keyed ignoring
+        // case, since only that line needs rewriting when the value changes.
+        let mut tag_lines = HashMap::new();
+        for (i, line) in self.lines.iter().enumerate() {
+            if let TocLine::Tag { key, .. } = &line.content {
+                tag_lines.insert(key.to_lowercase(), i);
+            }
+        }
+        let new_tags: Vec<_> = self
+            .ordered_tags()
+            .into_iter()
+            .filter(|(key, _)| !tag_lines.contains_key(&key.to_lowercase()))
+            .collect();
+        let write_new_tags = |writer: &mut dyn Write| -> std::io::Result<()> {
+            for (key, value) in &new_tags {
+                writeln!(writer, "## {}: {}", key, value)?;
+            }
+            Ok(())
+        };
+        let last_tag_line = self.lines.iter().rposition(|line| {
+            matches!(line.content, TocLine::Tag { .. }) && line.condition.is_none()
+        });
+        if last_tag_line.is_none() {
+            write_new_tags(&mut writer)?;
+        }
+
+        // The files from `next_file` on which haven't been written yet.
+        let mut remaining: HashMap<&str, usize> = HashMap::new();
+        for file in &self.files {
+            *remaining.entry(file).or_default() += 1;
+        }
+        let mut next_file = 0;
+
+        for (i, line) in self.lines.iter().enumerate() {
+            match &line.content {
+                TocLine::Tag { key, value, text } => match self.tag(key) {
+                    Some(current) if current != value && tag_lines[&key.to_lowercase()] == i => {
+                        writeln!(writer, "## {}: {}", key, current)?
+                    }
+                    Some(_) => writeln!(writer, "{}", text.trim_end())?,
+                    None => {}
+                },
+                TocLine::File { path, text } => {
+                    if remaining.get(path.as_str()).is_some_and(|&n| n > 0) {
+                        while self.files[next_file] != *path {
+                            writeln!(writer, "{}", self.files[next_file])?;
+                            *remaining.get_mut(self.files[next_file].as_str()).unwrap() -= 1;
+                            next_file += 1;
+                        }
+                        writeln!(writer, "{}", text.trim_end())?;
+                        *remaining.get_mut(path.as_str()).unwrap() -= 1;
+                        next_file += 1;
+                    }
+                }
+                TocLine::Directive { text, .. }
+                | TocLine::MalformedTag(text)
+                | TocLine::Comment(text) => writeln!(writer, "{}", text.trim_end())?,
+                TocLine::Blank => writeln!(writer)?,
+            }
+
+            if last_tag_line == Some(i) {
+                write_new_tags(&mut writer)?;
+            }
+        }
+
+        for file in &self.files[next_file..] {
+            writeln!(writer, "{}", file)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the Toc to the file at `path` as `to_writer` does, creating it or
+    /// replacing its contents. Missing parent directories aren't created.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use tocer::Toc;
+    /// let path = "Interface/AddOns/Bagnon/Bagnon.toc";
+    /// let mut toc = Toc::from_path(path).unwrap();
+    /// toc.set_tag("Version", "8.2.17");
+    /// toc.write_to_path(path).unwrap();
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_to_path<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let mut writer = std::io::BufWriter::new(File::create(path)?);
+        self.to_writer(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Render the tags as `## Key: Value` lines in the order they were first
+    /// seen, followed by one file path per line, each ending with `\n`. Unlike
+    /// `to_writer`, the source's spacing, comments, blank lines and directives
+    /// aren't reproduced, so formatting changes don't affect the output.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let toc: Toc = "##Interface:1\n\n  Foo.lua ".parse().unwrap();
+    /// assert_eq!(toc.to_canonical_string(), "## Interface: 1\nFoo.lua\n");
+    /// ```
+    pub fn to_canonical_string(&self) -> String {
+        let mut out = String::new();
+        for (key, value) in self.ordered_tags() {
+            out.push_str(&format!("## {}: {}\n", key, value));
+        }
+
+        for file in &self.files {
+            out.push_str(file);
+            out.push('\n');
+        }
+
+        out
+    }
+}
+
+impl FromStr for Toc {
+    type Err = TocError;
+
+    /// Create a Toc object from a string, as `parse_bytes` does.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::Toc;
+    /// let toc: Toc = "## Interface: 1".parse().unwrap();
+    /// dbg!(&toc.tags["Interface"]);
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Toc::parse_bytes(s.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BAGNON: &str = concat!(
+        "##Interface:11302\n",
+        "##Title: |cff20ff20Bagnon|r\n",
+        "## Author: Tuller & Jaliborc (João Cardoso)\n",
+        "## d : 8.2.16\n",
+        "# comment\n",
+        "## e : Bagnon_Sets \n",
+        "## bad comment\n",
+        "##   \t OptionalDeps : BagBrother, WoWUnit\n"
+    );
+
+    const FILES: &str = concat!(
+        "a.lua\n",
+        "b.lua\n",
+        "# comment\n",
+        "c.lua  \n",
+        "## bad comment\n",
+        "dir\\d.xml\n",
+        " f"
+    );
+
+    /// Creates an empty directory under the system temp dir that is unique to
+    /// the calling test.
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("tocer-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_empty_reader() {
+        let reader = std::io::Cursor::new("");
+        let toc = Toc::from_reader(reader).unwrap();
+        assert!(toc.tags.is_empty());
+        assert!(toc.files.is_empty());
+    }
+
+    #[test]
+    fn test_files() {
+        let reader = std::io::Cursor::new(FILES);
+        let toc = Toc::from_reader(reader).unwrap();
+        assert!(toc.tags.is_empty());
+        assert_eq!(toc.files.len(), 5);
+        assert_eq!(toc.files[0], "a.lua");
+        assert_eq!(toc.files[1], "b.lua");
+        assert_eq!(toc.files[2], "c.lua");
+        assert_eq!(toc.files[3], "dir\\d.xml");
+        assert_eq!(toc.files[4], "f");
+    }
+
+    #[test]
+    fn test_no_output() {
+        // The test harness captures what tests print, so parse in a child
+        // process which doesn't and check that all of its output is the
+        // harness's own.
+        if std::env::var_os("TOCER_TEST_NO_OUTPUT").is_some() {
+            let toc = Toc::from_reader(std::io::Cursor::new(FILES)).unwrap();
+            assert_eq!(toc.files.len(), 5);
+            return;
+        }
+
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "tests::test_no_output", "--nocapture"])
+            .args(["--test-threads", "1", "--color", "never"])
+            .env("TOCER_TEST_NO_OUTPUT", "1")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        for line in stdout.lines() {
+            assert!(
+                line.is_empty()
+                    || line.starts_with("running ")
+                    || line == "test tests::test_no_output ... ok"
+                    || line.starts_with("test result: "),
+                "unexpected output {:?}",
+                line
+            );
+        }
+        assert!(output.stderr.is_empty());
+    }
+
+    #[test]
+    fn test_tags() {
+        let reader = std::io::Cursor::new(BAGNON);
+        let toc = Toc::from_reader(reader).unwrap();
+        assert_eq!(toc.tags.len(), 6);
+        assert_eq!(toc.tags["Interface"], "11302");
+        assert_eq!(toc.tags["Title"], "|cff20ff20Bagnon|r");
+        assert_eq!(toc.tags["Author"], "Tuller & Jaliborc (João Cardoso)");
+        assert_eq!(toc.tags["d"], "8.2.16");
+        assert_eq!(toc.tags["e"], "Bagnon_Sets");
+        assert_eq!(toc.tags["OptionalDeps"], "BagBrother, WoWUnit");
+        assert!(toc.files.is_empty());
+        assert_eq!(
+            toc.warnings,
+            vec![Warning {
+                line: 7,
+                reason: "tag line missing ':'".to_string(),
+                text: "## bad comment".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_from_path() {
+        let dir = temp_dir("from_path");
+        let path = dir.join("Foo.toc");
+        std::fs::write(&path, "## Interface: 11302\n## Title: Foo\nFoo.lua\n").unwrap();
+
+        let toc = Toc::from_path(&path).unwrap();
+        assert_eq!(toc.tags.len(), 2);
+        assert_eq!(toc.tags["Interface"], "11302");
+        assert_eq!(toc.tags["Title"], "Foo");
+        assert_eq!(toc.files, vec!["Foo.lua"]);
+        assert_eq!(toc.dir.as_deref(), Some(dir.as_path()));
+        assert_eq!(toc.name.as_deref(), Some("Foo"));
+        assert_eq!(toc.resolved_files_in_dir(), Some(vec![dir.join("Foo.lua")]));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_from_addon_dir() {
+        let dir = temp_dir("from_addon_dir").join("Foo");
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join("Foo_Vanilla.toc"), "## Interface: 11504\n").unwrap();
+        std::fs::write(dir.join("Foo_Cata.toc"), "## Interface: 40400\n").unwrap();
+        std::fs::write(dir.join("Bar.toc"), "## Interface: 110002\n").unwrap();
+        std::fs::write(dir.join("Foo.lua"), "").unwrap();
+
+        let tocs = Toc::from_addon_dir(&dir).unwrap();
+        assert_eq!(tocs.len(), 2);
+        assert_eq!(tocs[&Flavor::Vanilla].tags["Interface"], "11504");
+        assert_eq!(tocs[&Flavor::Cata].tags["Interface"], "40400");
+
+        std::fs::write(dir.join("Foo.toc"), "## Interface: 110002\n").unwrap();
+        let tocs = Toc::from_addon_dir(&dir).unwrap();
+        assert_eq!(tocs[&Flavor::Mainline].name.as_deref(), Some("Foo"));
+
+        std::fs::write(dir.join("Foo_Mainline.toc"), "## Interface: 110005\n").unwrap();
+        let tocs = Toc::from_addon_dir(&dir).unwrap();
+        assert_eq!(tocs.len(), 3);
+        assert_eq!(tocs[&Flavor::Mainline].tags["Interface"], "110005");
+
+        std::fs::write(dir.join("Foo_BCC.toc"), "## Interface: 20504\n").unwrap();
+        std::fs::write(dir.join("Foo_TBC.toc"), "## Interface: 20505\n").unwrap();
+        let e = Toc::from_addon_dir(&dir).unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+        assert!(e.to_string().contains("are both for TBC"));
+
+        std::fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+
+    #[cfg(feature = "glob")]
+    #[test]
+    fn test_files_matching_glob() {
+        let dir = temp_dir("files_matching_glob");
+        std::fs::create_dir(dir.join("Modules")).unwrap();
+        for file in &["b.lua", "a.lua", "c.xml", "Modules/d.lua"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+
+        let toc = Toc::from_reader(std::io::Cursor::new("*.lua\n")).unwrap();
+        assert_eq!(
+            toc.files_matching_glob(&dir, "*.lua"),
+            vec![dir.join("a.lua"), dir.join("b.lua")]
+        );
+        assert_eq!(
+            toc.files_matching_glob(&dir, "Modules\\*.lua"),
+            vec![dir.join("Modules").join("d.lua")]
+        );
+        assert!(toc.files_matching_glob(&dir, "*.toc").is_empty());
+        assert!(toc.files_matching_glob(&dir, "[").is_empty());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_to_path() {
+        let dir = temp_dir("write_to_path");
+        let path = dir.join("Foo.toc");
+        let s = "## Title: Foo\n# comment\n\n## Interface: 110002\nFoo.lua\n";
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        toc.write_to_path(&path).unwrap();
+
+        let mut read = Toc::from_path(&path).unwrap();
+        assert_eq!(read.dir.take().as_deref(), Some(dir.as_path()));
+        assert_eq!(read.name.take().as_deref(), Some("Foo"));
+        assert_eq!(read, toc);
+
+        std::fs::write(
+            &path,
+            "longer contents which should be replaced\n".repeat(10),
+        )
+        .unwrap();
+        toc.write_to_path(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), s);
+
+        assert!(toc
+            .write_to_path(dir.join("missing").join("Foo.toc"))
+            .is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_from_path_not_found() {
+        let dir = temp_dir("from_path_not_found");
+        match Toc::from_path(dir.join("Missing.toc")) {
+            Err(TocError::Io { source, .. }) => {
+                assert_eq!(source.kind(), std::io::ErrorKind::NotFound)
+            }
+            _ => panic!("expected a NotFound I/O error"),
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_tag_order() {
+        let s = concat!(
+            "## Title: Foo\n",
+            "## Interface: 11302\n",
+            "## Author: Bar\n",
+            "## Interface: 110002\n"
+        );
+        let reader = std::io::Cursor::new(s);
+        let toc = Toc::from_reader(reader).unwrap();
+        assert_eq!(toc.tag_order, vec!["Title", "Interface", "Author"]);
+        assert_eq!(toc.tags["Interface"], "110002");
+    }
+
+    #[test]
+    fn test_to_writer_without_lines() {
+        let s = concat!(
+            "##Interface:11302\n",
+            "## Title: |cff20ff20Bagnon|r\n",
+            "# comment\n",
+            "## OptionalDeps : BagBrother, WoWUnit\n",
+            "a.lua\n",
+            "dir\\d.xml\n"
+        );
+        let mut toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        toc.lines.clear();
+        let mut out = Vec::new();
+        toc.to_writer(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out.clone()).unwrap(),
+            concat!(
+                "## Interface: 11302\n",
+                "## Title: |cff20ff20Bagnon|r\n",
+                "## OptionalDeps: BagBrother, WoWUnit\n",
+                "a.lua\n",
+                "dir\\d.xml\n"
+            )
+        );
+
+        let reparsed = Toc::from_reader(std::io::Cursor::new(out)).unwrap();
+        assert_eq!(reparsed.tags, toc.tags);
+        assert_eq!(reparsed.tag_order, toc.tag_order);
+        assert_eq!(reparsed.files, toc.files);
+    }
+
+    #[test]
+    fn test_io_error() {
+        struct FailingReader;
+
+        impl std::io::Read for FailingReader {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("boom"))
+            }
+        }
+
+        match Toc::from_reader(FailingReader) {
+            Err(TocError::Io { line, source }) => {
+                assert_eq!(line, None);
+                assert_eq!(source.to_string(), "boom");
+            }
+            _ => panic!("expected an I/O error"),
+        }
+    }
+
+    #[test]
+    fn test_tag_case_insensitive() {
+        let reader = std::io::Cursor::new("## Interface: 11302\n");
+        let toc = Toc::from_reader(reader).unwrap();
+        assert_eq!(toc.tag("Interface"), Some("11302"));
+        assert_eq!(toc.tag("interface"), Some("11302"));
+        assert_eq!(toc.tag("INTERFACE"), Some("11302"));
+        assert_eq!(toc.tag("Title"), None);
+    }
+
+    #[test]
+    fn test_interface_version() {
+        let reader = std::io::Cursor::new("## Interface: 110002 \n");
+        let toc = Toc::from_reader(reader).unwrap();
+        let version = toc.interface_version().unwrap();
+        assert_eq!((version.major, version.minor, version.patch), (11, 0, 2));
+
+        let reader = std::io::Cursor::new("## Interface: retail\n");
+        let toc = Toc::from_reader(reader).unwrap();
+        assert_eq!(toc.interface_version(), None);
+
+        let toc = Toc::from_reader(std::io::Cursor::new("")).unwrap();
+        assert_eq!(toc.interface_version(), None);
+    }
+
+    #[test]
+    fn test_is_compatible_with() {
+        let version = |s| InterfaceVersion::parse(s).unwrap();
+        let toc = Toc::from_reader(std::io::Cursor::new("## Interface: 110002\n")).unwrap();
+        assert!(toc.is_compatible_with(version("110005")));
+        assert!(!toc.is_compatible_with(version("100207")));
+
+        let toc = Toc::from_reader(std::io::Cursor::new("## Interface: 11504, 40400\n")).unwrap();
+        assert!(toc.is_compatible_with(version("40402")));
+        assert!(toc.is_compatible_with(version("11505")));
+        assert!(!toc.is_compatible_with(version("110002")));
+
+        let toc = Toc::from_reader(std::io::Cursor::new("")).unwrap();
+        assert!(!toc.is_compatible_with(version("110002")));
+    }
+
+    #[test]
+    fn test_interface_versions() {
+        let reader = std::io::Cursor::new("## Interface: 11302, bad,110002\n");
+        let toc = Toc::from_reader(reader).unwrap();
+        assert_eq!(
+            toc.interface_versions(),
+            vec![
+                InterfaceVersion::parse("11302").unwrap(),
+                InterfaceVersion::parse("110002").unwrap()
+            ]
+        );
+
+        let toc = Toc::from_reader(std::io::Cursor::new("")).unwrap();
+        assert!(toc.interface_versions().is_empty());
+    }
+
+    #[test]
+    fn test_dependencies() {
+        let s = concat!(
+            "## Dependencies: Foo, ,Bar\n",
+            "## RequiredDeps: Bar, Baz\n",
+            "## OptionalDeps: BagBrother, WoWUnit\n"
+        );
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        assert_eq!(toc.dependencies(), vec!["Foo", "Bar", "Baz"]);
+        assert_eq!(toc.optional_dependencies(), vec!["BagBrother", "WoWUnit"]);
+
+        let toc = Toc::from_reader(std::io::Cursor::new("")).unwrap();
+        assert!(toc.dependencies().is_empty());
+        assert!(toc.optional_dependencies().is_empty());
+    }
+
+    #[test]
+    fn test_dependencies_escaped_comma() {
+        let toc =
+            Toc::from_reader(std::io::Cursor::new("## Dependencies: Foo\\, Bar, Baz\n")).unwrap();
+        assert_eq!(toc.dependencies(), vec!["Foo, Bar", "Baz"]);
+        assert_eq!(split_list("a\\b,\\,"), vec!["a\\b", ","]);
+    }
+
+    #[test]
+    fn test_well_known_tags() {
+        let toc = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        assert_eq!(toc.title(), Some("|cff20ff20Bagnon|r"));
+        assert_eq!(toc.author(), Some("Tuller & Jaliborc (João Cardoso)"));
+        assert_eq!(toc.version(), None);
+        assert_eq!(toc.notes(), None);
+
+        let s = "## version: 8.2.16\n## Notes: Single window bags\n";
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        assert_eq!(toc.version(), Some("8.2.16"));
+        assert_eq!(toc.notes(), Some("Single window bags"));
+    }
+
+    #[test]
+    fn test_title_internal_whitespace() {
+        let toc = Toc::from_reader(std::io::Cursor::new("## Title:  Big   Bag \n")).unwrap();
+        assert_eq!(toc.title(), Some("Big   Bag"));
+    }
+
+    #[test]
+    fn test_title_plain() {
+        let toc = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        assert_eq!(toc.title_plain().as_deref(), Some("Bagnon"));
+
+        let toc = Toc::from_reader(std::io::Cursor::new("## Title: Bagnon\n")).unwrap();
+        assert_eq!(toc.title_plain().as_deref(), Some("Bagnon"));
+    }
+
+    #[test]
+    fn test_display_title() {
+        let toc = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        assert_eq!(toc.display_title("Folder"), "Bagnon");
+
+        let toc = Toc::from_reader(std::io::Cursor::new("## Interface: 110002\n")).unwrap();
+        assert_eq!(toc.display_title("Folder"), "Folder");
+    }
+
+    #[test]
+    fn test_title_icons() {
+        let s = "## Title: |TInterface\\Icons\\INV_Misc_Bag_08:16:16|t |cff20ff20Bagnon|r\n";
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        assert_eq!(toc.title_icons(), vec!["Interface\\Icons\\INV_Misc_Bag_08"]);
+        assert_eq!(toc.title_plain().as_deref(), Some(" Bagnon"));
+
+        let toc = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        assert!(toc.title_icons().is_empty());
+    }
+
+    #[test]
+    fn test_notes_plain() {
+        let s =
+            "## Notes: |cff00ff00Bagnon|r merges your bags.|nType |cffffd200/bgn|r for options\n";
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        assert_eq!(
+            toc.notes_plain().as_deref(),
+            Some("Bagnon merges your bags.\nType /bgn for options")
+        );
+
+        let toc = Toc::from_reader(std::io::Cursor::new("")).unwrap();
+        assert_eq!(toc.notes_plain(), None);
+    }
+
+    #[test]
+    fn test_byte_order_mark() {
+        let s = b"\xEF\xBB\xBF## Interface: 11302\n## Title: Foo\n";
+        let toc = Toc::from_reader(std::io::Cursor::new(&s[..])).unwrap();
+        assert_eq!(toc.tags["Interface"], "11302");
+        assert_eq!(toc.tag_order, vec!["Interface", "Title"]);
+    }
+
+    #[test]
+    fn test_from_str() {
+        let toc: Toc = BAGNON.parse().unwrap();
+        assert_eq!(toc.tags.len(), 6);
+        assert_eq!(toc.tags["Interface"], "11302");
+    }
+
+    #[test]
+    fn test_to_writer_round_trip() {
+        let s = concat!(
+            "##Interface:11302\n",
+            "## Title: |cff20ff20Bagnon|r  \n",
+            "\n",
+            "# Core\n",
+            "a.lua\n",
+            "   \n",
+            "## bad comment\n",
+            "# Frames\n",
+            "dir\\d.xml\n"
+        );
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        assert_eq!(toc.lines.len(), 9);
+        assert_eq!(toc.lines[2].content, TocLine::Blank);
+        assert_eq!(toc.lines[3].content, TocLine::Comment("# Core".to_string()));
+        assert_eq!(toc.files, vec!["a.lua", "dir\\d.xml"]);
+
+        let mut out = Vec::new();
+        toc.to_writer(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            s.replace("|r  ", "|r").replace("   \n", "\n")
+        );
+    }
+
+    #[test]
+    fn test_to_writer_reconciles_fields() {
+        let s = concat!(
+            "## Interface: 11302\n",
+            "## Title: Bagnon\n",
+            "## Notes: Bags\n",
+            "\n",
+            "# Core\n",
+            "a.lua\n",
+            "b.lua # main\n",
+            "# Frames\n",
+            "c.xml\n",
+            "# end\n"
+        );
+        let mut toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        toc.tags
+            .insert("Interface".to_string(), "110002".to_string());
+        toc.tags.remove("Notes");
+        toc.tags.insert("Author".to_string(), "Tuller".to_string());
+        toc.tags.insert("Version".to_string(), "1.0".to_string());
+        toc.tag_order.push("Version".to_string());
+        toc.files = vec!["a.lua", "new.lua", "b.lua # main", "d.lua"]
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+
+        let mut out = Vec::new();
+        toc.to_writer(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out.clone()).unwrap(),
+            concat!(
+                "## Interface: 110002\n",
+                "## Title: Bagnon\n",
+                "## Version: 1.0\n",
+                "## Author: Tuller\n",
+                "\n",
+                "# Core\n",
+                "a.lua\n",
+                "new.lua\n",
+                "b.lua # main\n",
+                "# Frames\n",
+                "# end\n",
+                "d.lua\n"
+            )
+        );
+
+        let reparsed = Toc::from_reader(std::io::Cursor::new(out)).unwrap();
+        assert_eq!(reparsed.tags, toc.tags);
+        assert_eq!(reparsed.files, toc.files);
+        let s = "#@retail@\n## A: 1\n#@end-retail@\n";
+        let mut toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        toc.tags.insert("B".to_string(), "2".to_string());
+        let mut out = Vec::new();
+        toc.to_writer(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), format!("## B: 2\n{}", s));
+    }
+
+    #[test]
+    fn test_localized_tag() {
+        let s = "## Title: English\n## Title-deDE: Deutsch\n## Title-xxXX: Unknown\n";
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        assert_eq!(toc.localized_tag("Title", "deDE"), Some("Deutsch"));
+        assert_eq!(toc.localized_tag("Title", "enUS"), Some("English"));
+        assert_eq!(toc.localized_tag("Title", "frFR"), Some("English"));
+        assert_eq!(toc.localized_tag("Title", "xxXX"), Some("English"));
+        assert_eq!(toc.localized_tag("Notes", "deDE"), None);
+    }
+
+    #[test]
+    fn test_to_canonical_string() {
+        let a: Toc = "## Title: Foo\n## Interface: 110002\nFoo.lua\n"
+            .parse()
+            .unwrap();
+        let b: Toc = "##Title:Foo  \r\n# comment\n###  Interface :  110002\n\n\tFoo.lua"
+            .parse()
+            .unwrap();
+        assert_eq!(a.to_canonical_string(), b.to_canonical_string());
+        assert_eq!(
+            a.to_canonical_string(),
+            "## Title: Foo\n## Interface: 110002\nFoo.lua\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_bytes() {
+        let toc = Toc::parse_bytes(BAGNON.as_bytes()).unwrap();
+        let expected = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        assert_eq!(toc.tags, expected.tags);
+        assert_eq!(toc.files, expected.files);
+        assert_eq!(toc.lines, expected.lines);
+
+        assert!(Toc::parse_bytes(b"").unwrap().lines.is_empty());
+        match Toc::parse_bytes(b"## Title: A\n## Notes: \xff\n") {
+            Err(TocError::Parse { line, reason, text }) => {
+                assert_eq!(line, 2);
+                assert_eq!(reason, "line is not valid UTF-8");
+                assert_eq!(text, "## Notes: \u{fffd}");
+            }
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_has_file() {
+        let toc = Toc::from_reader(std::io::Cursor::new(FILES)).unwrap();
+        assert!(toc.has_file("a.lua"));
+        assert!(toc.has_file("A.LUA"));
+        assert!(toc.has_file("dir/d.xml"));
+        assert!(toc.has_file("Dir\\D.xml"));
+        assert!(!toc.has_file("d.xml"));
+        assert!(!toc.has_file("e.lua"));
+    }
+
+    #[test]
+    fn test_files_sorted() {
+        let s = "b.lua\nDir\\z.xml\nA.lua\ndir/a.xml\na.lua\nc.lua\n";
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        assert_eq!(
+            toc.files_sorted(),
+            vec![
+                "A.lua",
+                "a.lua",
+                "b.lua",
+                "c.lua",
+                "dir/a.xml",
+                "Dir\\z.xml"
+            ]
+        );
+        assert_eq!(toc.files[0], "b.lua");
+    }
+
+    #[test]
+    fn test_entry_extension() {
+        assert_eq!(entry_extension("a.lua"), Some("lua"));
+        assert_eq!(entry_extension("Libs\\Lib.v2\\Embeds.XML"), Some("XML"));
+        assert_eq!(entry_extension("dir.d/LICENSE"), None);
+        assert_eq!(entry_extension(".lua"), None);
+        assert_eq!(entry_extension(""), None);
+    }
+
+    #[test]
+    fn test_classified_files() {
+        let toc = Toc::from_reader(std::io::Cursor::new(FILES)).unwrap();
+        assert_eq!(
+            toc.classified_files(),
+            vec![
+                ("a.lua", FileKind::Lua),
+                ("b.lua", FileKind::Lua),
+                ("c.lua", FileKind::Lua),
+                ("dir\\d.xml", FileKind::Xml),
+                ("f", FileKind::Other),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_from_reader_multi() {
+        let s = format!("{}---\n{}\n --- \n", BAGNON, FILES);
+        let tocs = Toc::from_reader_multi(std::io::Cursor::new(s)).unwrap();
+        assert_eq!(tocs.len(), 2);
+        assert_eq!(
+            tocs[0],
+            Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap()
+        );
+        assert_eq!(
+            tocs[1].files,
+            vec!["a.lua", "b.lua", "c.lua", "dir\\d.xml", "f"]
+        );
+        assert_eq!(tocs[1].lines[0].number, Some(1));
+
+        let s = "## Title: A\n===\n## Title: B\n---\n";
+        let tocs = Toc::from_reader_multi_with_separator(std::io::Cursor::new(s), "===").unwrap();
+        assert_eq!(tocs.len(), 2);
+        assert_eq!(tocs[1].files, vec!["---"]);
+
+        assert!(Toc::from_reader_multi(std::io::Cursor::new(""))
+            .unwrap()
+            .is_empty());
+
+        let s = format!("## Title: A\n---\n{}\n", "a".repeat(200 * 1024));
+        let e = Toc::from_reader_multi(std::io::Cursor::new(s)).unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+        assert!(e.to_string().starts_with("line 1: line is longer than"));
+    }
+
+    #[test]
+    fn test_unsupported_files() {
+        let s = "Core.lua\nnotes.txt\nLibs\\Embeds.XML\nLICENSE\nModule.toc\n";
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        assert_eq!(toc.unsupported_files(), vec!["notes.txt", "LICENSE"]);
+
+        let toc = Toc::from_reader(std::io::Cursor::new(FILES)).unwrap();
+        assert_eq!(toc.unsupported_files(), vec!["f"]);
+    }
+
+    #[test]
+    fn test_missing_final_newline() {
+        for s in &[
+            "## Title: Foo\n## Version: 1.0",
+            "## Title: Foo\r\n## Version: 1.0",
+        ] {
+            let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+            assert_eq!(toc.version(), Some("1.0"));
+            assert_eq!(toc.line_of_tag("Version"), Some(2));
+            assert_eq!(toc.lines.len(), 2);
+
+            let toc = Toc::parse_bytes(s.as_bytes()).unwrap();
+            assert_eq!(toc.version(), Some("1.0"));
+        }
+    }
+
+    #[test]
+    fn test_files_iter() {
+        let toc = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        assert!(toc.files_iter().eq(toc.files.iter().map(String::as_str)));
+        assert_eq!(toc.files_iter().count(), toc.files.len());
+    }
+
+    #[test]
+    fn test_files_normalized() {
+        let toc =
+            Toc::from_reader(std::io::Cursor::new("a.lua\ndir\\d.xml\nx/y\\\\z.lua\n")).unwrap();
+        assert_eq!(
+            toc.files_normalized(),
+            vec![
+                PathBuf::from("a.lua"),
+                Path::new("dir").join("d.xml"),
+                Path::new("x").join("y").join("z.lua")
+            ]
+        );
+        assert_eq!(toc.files[1], "dir\\d.xml");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_files_normalized_unix() {
+        let toc = Toc::from_reader(std::io::Cursor::new("dir\\d.xml")).unwrap();
+        assert_eq!(toc.files_normalized()[0].to_str(), Some("dir/d.xml"));
+    }
+
+    #[test]
+    fn test_resolved_files() {
+        let toc = Toc::from_reader(std::io::Cursor::new("a.lua\ndir\\d.xml\n")).unwrap();
+        let base = Path::new("/addons/Foo");
+        assert_eq!(
+            toc.resolved_files(base),
+            vec![base.join("a.lua"), base.join("dir").join("d.xml")]
+        );
+        assert_eq!(toc.resolved_files_in_dir(), None);
+    }
+
+    #[test]
+    fn test_missing_files() {
+        let dir = temp_dir("missing_files");
+        std::fs::create_dir(dir.join("dir")).unwrap();
+        std::fs::write(dir.join("dir").join("d.xml"), "").unwrap();
+
+        let toc = Toc::from_reader(std::io::Cursor::new("a.lua\ndir\\d.xml\n")).unwrap();
+        assert_eq!(toc.missing_files(&dir), vec![dir.join("a.lua")]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_files_with_extension() {
+        let toc = Toc::from_reader(std::io::Cursor::new(FILES)).unwrap();
+        assert_eq!(
+            toc.files_with_extension("lua"),
+            vec!["a.lua", "b.lua", "c.lua"]
+        );
+        assert_eq!(toc.files_with_extension("XML"), vec!["dir\\d.xml"]);
+        assert!(toc.files_with_extension("toc").is_empty());
+    }
+
+    #[test]
+    fn test_merge() {
+        let s = "## Interface: 110002\n## Title: Foo\n# Files\nCore.lua\n";
+        let mut toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        let s = "## Interface: 11302\n## X-Flavor: Classic\nCore.lua\nClassic.lua\n";
+        let other = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        toc.merge(other);
+
+        assert_eq!(toc.tags["Interface"], "11302");
+        assert_eq!(toc.tags["Title"], "Foo");
+        assert_eq!(toc.tag_order, vec!["Interface", "Title", "X-Flavor"]);
+        assert_eq!(toc.files, vec!["Core.lua", "Classic.lua"]);
+
+        let mut out = Vec::new();
+        toc.to_writer(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "## Interface: 11302\n",
+                "## Title: Foo\n",
+                "## X-Flavor: Classic\n",
+                "# Files\n",
+                "Core.lua\n",
+                "Classic.lua\n"
+            )
+        );
+
+        let mut other: Toc = "## Title: Bar\nA.lua\nA.lua\n".parse().unwrap();
+        other.tag_order.push("Missing".to_string());
+        toc.merge(other);
+        assert_eq!(toc.tags["Title"], "Bar");
+        assert_eq!(toc.files, vec!["Core.lua", "Classic.lua", "A.lua"]);
+
+        let mut other = Toc::default();
+        other.files.push("x.lua\n## Interface: 9".to_string());
+        toc.merge(other);
+        let mut out = Vec::new();
+        toc.to_writer(&mut out).unwrap();
+        let reparsed = Toc::from_reader(std::io::Cursor::new(out)).unwrap();
+        assert_eq!(reparsed.tags["Interface"], "11302");
+        assert_eq!(reparsed.files.last().unwrap(), "x.lua## Interface: 9");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let toc = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        let json = serde_json::to_string(&toc).unwrap();
+        let parsed: Toc = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.tags, toc.tags);
+        assert_eq!(parsed.tag_order, toc.tag_order);
+        assert_eq!(parsed.files, toc.files);
+        assert_eq!(parsed.warnings, toc.warnings);
+        assert_eq!(parsed.lines, toc.lines);
+        assert_eq!(parsed.dir, toc.dir);
+    }
+
+    #[test]
+    fn test_lowercase_keys() {
+        let options = ParseOptions {
+            lowercase_keys: true,
+            ..Default::default()
+        };
+        let s = "## Interface: 110002\n## X-Website: https://example.com\n";
+        let toc = Toc::from_reader_with_options(std::io::Cursor::new(s), options).unwrap();
+        assert_eq!(toc.tag_order, vec!["interface", "x-website"]);
+        assert_eq!(toc.tags["interface"], "110002");
+        assert_eq!(toc.tag("Interface"), Some("110002"));
+        assert_eq!(toc.lines[0].number, Some(1));
+    }
+
+    #[test]
+    fn test_on_progress() {
+        let progress = std::rc::Rc::new(std::cell::Cell::new(0));
+        let sink = progress.clone();
+        let options = ParseOptions {
+            on_progress: Some(Box::new(move |bytes| sink.set(bytes))),
+            ..Default::default()
+        };
+        Toc::from_reader_with_options(std::io::Cursor::new(BAGNON), options).unwrap();
+        assert_eq!(progress.get(), BAGNON.len() as u64);
+    }
+
+    #[test]
+    fn test_skip_files() {
+        let options = ParseOptions {
+            skip_files: true,
+            ..Default::default()
+        };
+        let s = format!("{}{}", BAGNON, FILES);
+        let toc = Toc::from_reader_with_options(std::io::Cursor::new(s), options).unwrap();
+        assert_eq!(toc.tags.len(), 6);
+        assert_eq!(toc.tags["Title"], "|cff20ff20Bagnon|r");
+        assert!(toc.files.is_empty());
+    }
+
+    #[test]
+    fn test_strict() {
+        let strict = || ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        match Toc::from_reader_with_options(std::io::Cursor::new(BAGNON), strict()) {
+            Err(e @ TocError::Parse { .. }) => {
+                assert_eq!(
+                    e.to_string(),
+                    "line 7: tag line missing ':': \"## bad comment\""
+                );
+            }
+            _ => panic!("expected a parse error"),
+        }
+
+        let toc = Toc::from_reader_with_options(
+            std::io::Cursor::new(FILES.replace("## bad comment\n", "")),
+            strict(),
+        )
+        .unwrap();
+        assert_eq!(toc.files.len(), 5);
+
+        let toc = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        assert_eq!(toc.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_directives() {
+        let s = concat!(
+            "## Title: Foo\n",
+            "# comment\n",
+            "#@no-lib-strip@\n",
+            "Libs\\LibStub.lua\n",
+            "#@end-no-lib-strip@\n",
+            "# @not a directive@\n",
+            "Core.lua\n"
+        );
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        assert_eq!(toc.directives, vec!["no-lib-strip", "end-no-lib-strip"]);
+        assert_eq!(toc.files, vec!["Libs\\LibStub.lua", "Core.lua"]);
+        assert_eq!(
+            toc.lines[5].content,
+            TocLine::Comment("# @not a directive@".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_lines_is_lazy() {
+        /// Yields one line per read and counts how many reads were made.
+        struct LineReader<'a> {
+            lines: std::slice::Iter<'a, &'a str>,
+            reads: &'a std::cell::Cell<usize>,
+        }
+
+        impl std::io::Read for LineReader<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                self.reads.set(self.reads.get() + 1);
+                let line = self.lines.next().map_or(&b""[..], |line| line.as_bytes());
+                buf[..line.len()].copy_from_slice(line);
+                Ok(line.len())
+            }
+        }
+
+        let reads = std::cell::Cell::new(0);
+        let lines = ["## Interface: 1\n", "## Title: Foo\n", "a.lua\n", "b.lua\n"];
+        let reader = LineReader {
+            lines: lines.iter(),
+            reads: &reads,
+        };
+
+        let parsed: Vec<_> = Toc::parse_lines(reader).take(2).collect();
+        assert_eq!(reads.get(), 2);
+        assert!(matches!(&parsed[0], Ok(TocLine::Tag { key, .. }) if key == "Interface"));
+        assert!(matches!(&parsed[1], Ok(TocLine::Tag { key, .. }) if key == "Title"));
+    }
+
+    #[test]
+    fn test_spans() {
+        let s = "## Title: Foo\r\nCore.lua\n";
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        let span = toc.lines[1].span.clone().unwrap();
+        assert_eq!(span, 15..23);
+        assert_eq!(&s[span], "Core.lua");
+        assert_eq!(toc.lines[0].span, Some(0..13));
+
+        let mut toc = Toc::from_reader(std::io::Cursor::new("\u{feff}a.lua")).unwrap();
+        assert_eq!(toc.lines[0].span, Some(3..8));
+        toc.set_tag("Title", "Foo");
+        assert_eq!(toc.lines[0].span, None);
+    }
+
+    #[test]
+    fn test_conditions() {
+        let s = "a.lua\n#@no-lib-strip@\nlibs\\LibStub.lua\n#@end-no-lib-strip@\nb.lua\n";
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        let conditions: Vec<_> = toc.lines.iter().map(|l| l.condition.as_deref()).collect();
+        assert_eq!(
+            conditions,
+            vec![None, None, Some("no-lib-strip"), None, None]
+        );
+        assert_eq!(toc.files, vec!["a.lua", "libs\\LibStub.lua", "b.lua"]);
+    }
+
+    #[test]
+    fn test_file_looks_like_tag() {
+        let toc = Toc::from_reader(std::io::Cursor::new("Version: 1.0\na.lua\n")).unwrap();
+        assert_eq!(toc.files, vec!["Version: 1.0", "a.lua"]);
+        assert_eq!(toc.warnings.len(), 1);
+        assert_eq!(
+            toc.warnings[0].to_string(),
+            "line 1: file entry looks like a tag missing its '##': \"Version: 1.0\""
+        );
+
+        let options = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let toc =
+            Toc::from_reader_with_options(std::io::Cursor::new("Version: 1.0\n"), options).unwrap();
+        assert_eq!(toc.files, vec!["Version: 1.0"]);
+        assert_eq!(toc.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_malformed_tag_is_not_a_comment() {
+        let s = "# comment\n## bad comment\n";
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        assert_eq!(
+            toc.lines,
+            vec![
+                SourceLine {
+                    number: Some(1),
+                    content: TocLine::Comment("# comment".to_string()),
+                    condition: None,
+                    span: Some(0..9),
+                },
+                SourceLine {
+                    number: Some(2),
+                    content: TocLine::MalformedTag("## bad comment".to_string()),
+                    condition: None,
+                    span: Some(10..24),
+                }
+            ]
+        );
+        assert_eq!(toc.warnings.len(), 1);
+        assert_eq!(
+            toc.warnings[0].to_string(),
+            "line 2: tag line missing ':': \"## bad comment\""
+        );
+    }
+
+    #[test]
+    fn test_duplicate_tags_ignore_case() {
+        let s = "## Title: A\n## title: B\n";
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        assert_eq!(toc.tags.len(), 1);
+        assert_eq!(toc.tags["Title"], "B");
+        assert_eq!(toc.tag_order, vec!["Title"]);
+        assert_eq!(toc.duplicate_tags, vec![(2, "Title".to_string())]);
+        assert_eq!(toc.line_of_tag("Title"), Some(2));
+    }
+
+    #[test]
+    fn test_duplicate_tags() {
+        let s = "## Author: Foo\n## Title: Bar\n## Author: Baz\n";
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        assert_eq!(toc.tags["Author"], "Baz");
+        assert_eq!(toc.tag_order, vec!["Author", "Title"]);
+        assert_eq!(toc.duplicate_tags, vec![(3, "Author".to_string())]);
+
+        let toc = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        assert!(toc.duplicate_tags.is_empty());
+    }
+
+    #[test]
+    fn test_crlf() {
+        let lf = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        let crlf = Toc::from_reader(std::io::Cursor::new(BAGNON.replace('\n', "\r\n"))).unwrap();
+        assert_eq!(crlf.tags, lf.tags);
+        assert_eq!(crlf.tag_order, lf.tag_order);
+        assert_eq!(crlf.files, lf.files);
+        assert_eq!(crlf.warnings, lf.warnings);
+        assert_eq!(crlf.lines, lf.lines);
+        assert_ne!(crlf.lines[1].span, lf.lines[1].span);
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_from_reader_with_encoding() {
+        let le: Vec<u8> = std::iter::once(0xfeff)
+            .chain(BAGNON.encode_utf16())
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let be: Vec<u8> = std::iter::once(0xfeff)
+            .chain(BAGNON.encode_utf16())
+            .flat_map(u16::to_be_bytes)
+            .collect();
+
+        for bytes in &[le, be, BAGNON.as_bytes().to_vec()] {
+            let reader = std::io::Cursor::new(bytes);
+            let toc = Toc::from_reader_with_encoding(reader, ParseOptions::default()).unwrap();
+            assert_eq!(toc.tags.len(), 6);
+            assert_eq!(toc.tag_order[0], "Interface");
+            assert_eq!(toc.tags["Author"], "Tuller & Jaliborc (João Cardoso)");
+        }
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_from_reader_with_encoding_detect_utf16() {
+        let le: Vec<u8> = BAGNON.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let options = || ParseOptions {
+            detect_utf16: true,
+            ..Default::default()
+        };
+
+        let toc = Toc::from_reader_with_encoding(std::io::Cursor::new(&le), options()).unwrap();
+        assert_eq!(toc.tags.len(), 6);
+        assert_eq!(toc.tags["Author"], "Tuller & Jaliborc (João Cardoso)");
+
+        let reader = std::io::Cursor::new(BAGNON);
+        let toc = Toc::from_reader_with_encoding(reader, options()).unwrap();
+        assert_eq!(toc.tags.len(), 6);
+
+        let reader = std::io::Cursor::new(&le);
+        let result = Toc::from_reader_with_encoding(reader, ParseOptions::default());
+        assert!(result.map_or(true, |toc| toc.tags.len() != 6));
+
+        // An over-long line is reported without reading the rest of it.
+        struct Utf16Letters(std::rc::Rc<std::cell::Cell<usize>>);
+        impl std::io::Read for Utf16Letters {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let n = buf.len() & !1;
+                for pair in buf[..n].chunks_exact_mut(2) {
+                    pair.copy_from_slice(b"a\0");
+                }
+                self.0.set(self.0.get() + n);
+                Ok(n)
+            }
+        }
+        let read = std::rc::Rc::new(std::cell::Cell::new(0));
+        let reader = Utf16Letters(read.clone());
+        match Toc::from_reader_with_encoding(reader, options()) {
+            Err(TocError::Parse {
+                line: 1, reason, ..
+            }) => {
+                assert_eq!(reason, "line is longer than 65536 bytes");
+            }
+            result => panic!("unexpected {:?}", result),
+        }
+        assert!(read.get() < 1024 * 1024);
+
+        assert!(!looks_like_utf16le(b""));
+        assert!(!looks_like_utf16le(b"## Title: Foo\n"));
+    }
+
+    #[test]
+    fn test_addon_name_from_path() {
+        let name = |path: &str| Toc::addon_name_from_path(Path::new(path));
+        assert_eq!(name("Bagnon.toc").as_deref(), Some("Bagnon"));
+        assert_eq!(
+            name("Bagnon/Bagnon_Mainline.toc").as_deref(),
+            Some("Bagnon")
+        );
+        assert_eq!(name("Bagnon-Classic.toc").as_deref(), Some("Bagnon"));
+        assert_eq!(name("Bagnon_Sets.toc").as_deref(), Some("Bagnon_Sets"));
+        assert_eq!(name("").as_deref(), None);
+    }
+
+    #[test]
+    fn test_flavor_from_path() {
+        let flavor = |path: &str| Toc::flavor_from_path(Path::new(path));
+        assert_eq!(flavor("Foo/Foo_Vanilla.toc"), Some(Flavor::Vanilla));
+        assert_eq!(flavor("Foo_Mainline.toc"), Some(Flavor::Mainline));
+        assert_eq!(flavor("Foo.toc"), None);
+        assert_eq!(flavor("Foo_Sets.toc"), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        let toc = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        assert_eq!(toc.validate(), Ok(()));
+
+        let toc = Toc::from_reader(std::io::Cursor::new("## Title: Foo\n")).unwrap();
+        assert_eq!(
+            toc.validate(),
+            Err(vec!["missing Interface tag".to_string()])
+        );
+
+        let toc = Toc::from_reader(std::io::Cursor::new("## Interface: retail\n")).unwrap();
+        assert_eq!(
+            toc.validate(),
+            Err(vec![
+                "invalid Interface tag: \"retail\"".to_string(),
+                "missing Title tag".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_validate_interface_digits() {
+        let validate = |interface: &str| {
+            let s = format!("## Title: Foo\n## Interface: {}\n", interface);
+            Toc::from_reader(std::io::Cursor::new(s))
+                .unwrap()
+                .validate()
+        };
+        assert_eq!(validate("110002"), Ok(()));
+        assert_eq!(validate("11302, 110002"), Ok(()));
+        assert_eq!(
+            validate("123"),
+            Err(vec![
+                "suspicious Interface version \"123\": expected 5 or 6 digits".to_string()
+            ])
+        );
+        assert_eq!(
+            validate("11302, 1100002"),
+            Err(vec![
+                "suspicious Interface version \"1100002\": expected 5 or 6 digits".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_dedup_files() {
+        let s = "Core.lua\nOptions.lua\n# again\nCore.lua\n";
+        let mut toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        assert_eq!(toc.files, vec!["Core.lua", "Options.lua", "Core.lua"]);
+
+        toc.dedup_files();
+        assert_eq!(toc.files, vec!["Core.lua", "Options.lua"]);
+        let mut out = Vec::new();
+        toc.to_writer(&mut out).unwrap();
+        assert_eq!(out, b"Core.lua\nOptions.lua\n# again\n");
+    }
+
+    #[test]
+    fn test_line_of_tag() {
+        let toc = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        assert_eq!(toc.line_of_tag("Interface"), Some(1));
+        assert_eq!(toc.line_of_tag("Title"), Some(2));
+        assert_eq!(toc.line_of_tag("e"), Some(6));
+        assert_eq!(toc.line_of_tag("optionaldeps"), Some(8));
+        assert_eq!(toc.line_of_tag("Missing"), None);
+
+        let toc = Toc::from_reader(std::io::Cursor::new("## a: 1\nb.lua\n## a: 2\n")).unwrap();
+        assert_eq!(toc.line_of_tag("a"), Some(3));
+        assert_eq!(toc.lines[1].number, Some(2));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_from_async_reader() {
+        let reader = tokio::io::BufReader::new(std::io::Cursor::new(BAGNON));
+        let toc = Toc::from_async_reader(reader).await.unwrap();
+        let expected = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        assert_eq!(toc.tags, expected.tags);
+        assert_eq!(toc.tag_order, expected.tag_order);
+        assert_eq!(toc.warnings, expected.warnings);
+        assert_eq!(toc.lines, expected.lines);
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn test_from_zip() {
+        use std::io::{Cursor, Write};
+
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        writer.start_file("Bagnon/Bagnon.toc", options).unwrap();
+        writer.write_all(BAGNON.as_bytes()).unwrap();
+        writer.start_file("Bagnon/main.lua", options).unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+
+        let toc = Toc::from_zip(Cursor::new(&archive), "Bagnon").unwrap();
+        let expected = Toc::from_reader(Cursor::new(BAGNON)).unwrap();
+        assert_eq!(toc.tags, expected.tags);
+        assert_eq!(toc.name.as_deref(), Some("Bagnon"));
+
+        match Toc::from_zip(Cursor::new(&archive), "Missing") {
+            Err(TocError::Io { source: e, .. }) => {
+                assert_eq!(e.kind(), std::io::ErrorKind::NotFound);
+                assert_eq!(e.to_string(), "Missing/Missing.toc is not in the archive");
+            }
+            _ => panic!("expected a NotFound error"),
+        }
+    }
+
+    #[test]
+    fn test_check_flavor_consistency() {
+        let toc = Toc::from_reader(std::io::Cursor::new("## Interface: 110002\n")).unwrap();
+        assert_eq!(
+            toc.check_flavor_consistency(Path::new("Foo/Foo_Vanilla.toc")),
+            Some(
+                "Foo_Vanilla.toc is for Vanilla but its Interface tag \"110002\" is for another client"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            toc.check_flavor_consistency(Path::new("Foo_Mainline.toc")),
+            None
+        );
+        assert_eq!(toc.check_flavor_consistency(Path::new("Foo.toc")), None);
+
+        let toc = Toc::from_reader(std::io::Cursor::new("## Interface: 11504, 40400\n")).unwrap();
+        assert_eq!(
+            toc.check_flavor_consistency(Path::new("Foo_Vanilla.toc")),
+            None
+        );
+        assert_eq!(
+            toc.check_flavor_consistency(Path::new("Foo_Cata.toc")),
+            None
+        );
+        assert!(toc
+            .check_flavor_consistency(Path::new("Foo_TBC.toc"))
+            .is_some());
+    }
+
+    #[test]
+    fn test_tag_or() {
+        let toc = Toc::from_reader(std::io::Cursor::new("## Version: 1.2.3\n")).unwrap();
+        assert_eq!(toc.tag_or("version", "0.0.0"), "1.2.3");
+        assert_eq!(toc.tag_or("Title", "Unknown"), "Unknown");
+    }
+
+    #[test]
+    fn test_iter_tags() {
+        let toc = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        let tags: Vec<_> = toc.iter_tags().collect();
+        assert_eq!(
+            tags,
+            vec![
+                ("Interface", "11302"),
+                ("Title", "|cff20ff20Bagnon|r"),
+                ("Author", "Tuller & Jaliborc (João Cardoso)"),
+                ("d", "8.2.16"),
+                ("e", "Bagnon_Sets"),
+                ("OptionalDeps", "BagBrother, WoWUnit")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_key() {
+        let toc = Toc::from_reader(std::io::Cursor::new("## : x\n## Title: Foo\n")).unwrap();
+        assert_eq!(toc.tag_order, vec!["Title"]);
+        assert!(!toc.tags.contains_key(""));
+        assert_eq!(
+            toc.warnings,
+            vec![Warning {
+                line: 1,
+                reason: "tag line has an empty key".to_string(),
+                text: "## : x".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_strip_inline_comments() {
+        let s = "## Interface: 110002 # retail\n## Title: Foo#1\n";
+        let options = ParseOptions {
+            strip_inline_comments: true,
+            ..Default::default()
+        };
+        let toc = Toc::from_reader_with_options(std::io::Cursor::new(s), options).unwrap();
+        assert_eq!(toc.tags["Interface"], "110002");
+        assert_eq!(toc.tags["Title"], "Foo#1");
+
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        assert_eq!(toc.tags["Interface"], "110002 # retail");
+    }
+
+    #[cfg(feature = "semver")]
+    #[test]
+    fn test_semver() {
+        let semver = |s: &str| Toc::from_str(s).unwrap().semver();
+        assert_eq!(
+            semver("## Version: 8.2.16"),
+            Some(semver::Version::new(8, 2, 16))
+        );
+        assert_eq!(
+            semver("## Version: v1.2.3"),
+            Some(semver::Version::new(1, 2, 3))
+        );
+        assert_eq!(semver("## Version: 2024-01-31"), None);
+        assert_eq!(semver("## Title: Foo"), None);
+    }
+
+    #[test]
+    fn test_load_tags() {
+        let s = "## LoadOnDemand: 1\n## LoadWith: Blizzard_AuctionUI\n## LoadManagers: Foo, Bar\n";
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        assert!(toc.load_on_demand());
+        assert_eq!(toc.load_with(), vec!["Blizzard_AuctionUI"]);
+        assert_eq!(toc.load_managers(), vec!["Foo", "Bar"]);
+
+        let toc = Toc::from_reader(std::io::Cursor::new("## LoadOnDemand: 0\n")).unwrap();
+        assert!(!toc.load_on_demand());
+        assert!(toc.load_with().is_empty());
+        assert!(toc.load_managers().is_empty());
+
+        let toc = Toc::from_reader(std::io::Cursor::new("")).unwrap();
+        assert!(!toc.load_on_demand());
+    }
+
+    #[test]
+    fn test_eq() {
+        let a = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        let b = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        assert_eq!(a, b);
+
+        let a: Toc = "a.lua\nb.lua\n".parse().unwrap();
+        let b: Toc = "b.lua\na.lua\n".parse().unwrap();
+        assert_ne!(a, b);
+        let a = TocBuilder::new().tag("A", "1").tag("B", "2").build();
+        let b = TocBuilder::new().tag("B", "2").tag("A", "1").build();
+        assert_eq!(a, b);
+
+        let parsed: Toc = "## A: 1\n# comment\n##B:2\n".parse().unwrap();
+        assert_eq!(parsed, a);
+    }
+
+    #[test]
+    fn test_clone() {
+        let toc = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        let mut classic = toc.clone();
+        assert_eq!(classic, toc);
+
+        classic.set_tag("Interface", "11504");
+        assert_ne!(classic, toc);
+        assert_eq!(toc.tags["Interface"], "11302");
+    }
+
+    #[test]
+    fn test_raw_tag() {
+        let toc = Toc::from_reader(std::io::Cursor::new("## d :  8.2.16  \r\n## e:\n")).unwrap();
+        assert_eq!(toc.tag("d"), Some("8.2.16"));
+        assert_eq!(toc.raw_tag("d"), Some("  8.2.16  "));
+        assert_eq!(toc.raw_tag("E"), Some(""));
+        assert_eq!(toc.raw_tag("missing"), None);
+
+        let toc = TocBuilder::new().tag("d", "1").build();
+        assert_eq!(toc.raw_tag("d"), None);
+    }
+
+    #[test]
+    fn test_tag_contains() {
+        let toc = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        assert!(toc.tag_contains("João"));
+        assert!(toc.tag_contains("JALIBORC"));
+        assert!(!toc.tag_contains("nonexistent"));
+    }
+
+    #[test]
+    fn test_parse_with_bagnon() {
+        let (mut tags, mut comments, mut warnings) = (0, 0, 0);
+        Toc::parse_with(std::io::Cursor::new(BAGNON), |event| {
+            match event {
+                TocEvent::Tag { .. } => tags += 1,
+                TocEvent::Comment { .. } => comments += 1,
+                TocEvent::Warning(_) => warnings += 1,
+                TocEvent::File { .. } => panic!("unexpected file"),
+            }
+            std::ops::ControlFlow::Continue(())
+        })
+        .unwrap();
+        assert_eq!((tags, comments, warnings), (6, 1, 1));
+    }
+
+    #[test]
+    fn test_into_tags_and_files() {
+        let toc = Toc::from_reader(std::io::Cursor::new(FILES)).unwrap();
+        let expected = toc.files.clone();
+        assert_eq!(toc.into_files(), expected);
+
+        let mut toc: Toc = "## b: 1\n## a: 2\n## B: 3\n".parse().unwrap();
+        toc.tags.insert("d".to_string(), "4".to_string());
+        toc.tags.insert("c".to_string(), "5".to_string());
+        assert_eq!(
+            toc.into_tags(),
+            vec![
+                ("b".to_string(), "3".to_string()),
+                ("a".to_string(), "2".to_string()),
+                ("c".to_string(), "5".to_string()),
+                ("d".to_string(), "4".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tags_sorted() {
+        let toc = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        let sorted = toc.tags_sorted();
+        let keys: Vec<_> = sorted.keys().copied().collect();
+        assert_eq!(
+            keys,
+            vec!["Author", "Interface", "OptionalDeps", "Title", "d", "e"]
+        );
+        assert_eq!(sorted["Interface"], "11302");
+
+        let mut toc = toc;
+        toc.tags.insert("Z".to_string(), "1".to_string());
+        assert_eq!(toc.tags_sorted().len(), toc.tag_count());
+    }
+
+    #[test]
+    fn test_counts() {
+        let toc = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        assert_eq!(toc.tag_count(), 6);
+        assert_eq!(toc.file_count(), 0);
+
+        let toc = Toc::from_reader(std::io::Cursor::new("a.lua\na.lua\n")).unwrap();
+        assert_eq!(toc.tag_count(), 0);
+        assert_eq!(toc.file_count(), 2);
+    }
+
+    #[test]
+    fn test_set_tag_round_trip() {
+        let s = concat!(
+            "## Title: Foo\n",
+            "## Version: 1.0.0\n",
+            "# Load order matters\n",
+            "\n",
+            "#@no-lib-strip@\n",
+            "libs\\LibStub.lua\n",
+            "#@end-no-lib-strip@\n",
+            "Foo.lua\n"
+        );
+        let mut toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        toc.set_tag("Version", "1.0.1");
+        let mut out = Vec::new();
+        toc.to_writer(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        let changed: Vec<_> = s.lines().zip(out.lines()).filter(|(a, b)| a != b).collect();
+        assert_eq!(changed, vec![("## Version: 1.0.0", "## Version: 1.0.1")]);
+        assert_eq!(s.lines().count(), out.lines().count());
+
+        toc.set_tag("Author", "Bar");
+        assert_eq!(toc.tag_order, vec!["Title", "Version", "Author"]);
+        assert_eq!(toc.line_of_tag("Author"), None);
+
+        let s = "#@retail@\n## A: 1\n#@end-retail@\nFoo.lua\n";
+        let mut conditional = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        conditional.set_tag("B", "2");
+        let mut out = Vec::new();
+        conditional.to_writer(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out.clone()).unwrap(),
+            format!("## B: 2\n{}", s)
+        );
+        let reparsed = Toc::from_reader(std::io::Cursor::new(out)).unwrap();
+        assert_eq!(reparsed.lines[0].condition, None);
+
+        toc.set_tag("Interface", "1\r\nevil.lua");
+        assert_eq!(toc.tag("Interface"), Some("1evil.lua"));
+        let mut out = Vec::new();
+        toc.to_writer(&mut out).unwrap();
+        let reparsed = Toc::from_reader(std::io::Cursor::new(out)).unwrap();
+        assert_eq!(reparsed.files, toc.files);
+        assert_eq!(reparsed.tags["Interface"], "1evil.lua");
+    }
+
+    #[test]
+    fn test_rename_tag() {
+        let mut toc = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        assert!(toc.rename_tag("d", "Date"));
+        assert_eq!(toc.tags["Date"], "8.2.16");
+        assert!(!toc.tags.contains_key("d"));
+        assert_eq!(
+            toc.tag_order,
+            vec!["Interface", "Title", "Author", "Date", "e", "OptionalDeps"]
+        );
+        assert_eq!(toc.line_of_tag("Date"), Some(4));
+        assert!(!toc.rename_tag("d", "Date"));
+
+        let mut out = Vec::new();
+        toc.to_writer(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("\n## Date: 8.2.16\n"));
+
+        assert!(toc.rename_tag("Title", "X\r\nevil.lua"));
+        assert!(toc.tags.contains_key("Xevil.lua"));
+        let mut out = Vec::new();
+        toc.to_writer(&mut out).unwrap();
+        let reparsed = Toc::from_reader(std::io::Cursor::new(out)).unwrap();
+        assert_eq!(reparsed.files, toc.files);
+        assert!(reparsed.tags.contains_key("Xevil.lua"));
+    }
+
+    #[test]
+    fn test_rename_tag_collision() {
+        let mut toc: Toc = "## a: 1\n## b: 2\n## c: 3\n".parse().unwrap();
+        assert!(toc.rename_tag("a", "C"));
+        assert_eq!(toc.tag_order, vec!["C", "b"]);
+        assert_eq!(toc.tags["C"], "1");
+        assert_eq!(toc.lines.len(), 2);
+
+        assert!(toc.rename_tag("c", "c"));
+        assert_eq!(toc.tag_order, vec!["c", "b"]);
+        assert_eq!(toc.tags["c"], "1");
+    }
+
+    #[test]
+    fn test_tag_keys() {
+        let toc = Toc::from_reader(std::io::Cursor::new(BAGNON)).unwrap();
+        let keys: Vec<_> = toc.tag_keys().collect();
+        assert_eq!(
+            keys,
+            vec!["Interface", "Title", "Author", "d", "e", "OptionalDeps"]
+        );
+    }
+
+    #[test]
+    fn test_custom_tags() {
+        let s = "## Title: Foo\n## X-Website: https://example.com\n## x-Curse-Project-ID: 42\n";
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        let custom = toc.custom_tags();
+        assert_eq!(custom.len(), 2);
+        assert_eq!(custom["Website"], "https://example.com");
+        assert_eq!(custom["Curse-Project-ID"], "42");
+        assert_eq!(toc.tag("X-Website"), Some("https://example.com"));
+    }
+
+    #[test]
+    fn test_is_library() {
+        for s in &[
+            "## LoadOnDemand: 1\n",
+            "## X-Category: library\n",
+            "## Title: LibDataBroker-1.1\n",
+            "## Title: |cff00ff00LibStub|r\n",
+        ] {
+            let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+            assert!(toc.is_library(), "{:?}", s);
+        }
+
+        let s = "## Title: Libra\n## X-Category: Bags\n## LoadOnDemand: 0\n";
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        assert!(!toc.is_library());
+
+        let mut toc = Toc::from_reader(std::io::Cursor::new("## Title: Bagnon\n")).unwrap();
+        assert!(!toc.is_library());
+        toc.name = Some("LibSharedMedia-3.0".to_string());
+        assert!(toc.is_library());
+    }
+
+    #[test]
+    fn test_secure_and_allowed_game_types() {
+        let s = "## Secure: 1\n## AllowLoadGameType: standard, plunderstorm\n";
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        assert!(toc.secure());
+        assert_eq!(toc.allowed_game_types(), vec!["standard", "plunderstorm"]);
+
+        let toc = Toc::from_reader(std::io::Cursor::new("")).unwrap();
+        assert!(!toc.secure());
+        assert!(toc.allowed_game_types().is_empty());
+    }
+
+    #[test]
+    fn test_saved_variables() {
+        let s = "## SavedVariables: BagnonDB, BagnonSets\n## SavedVariablesPerCharacter:\n";
+        let toc = Toc::from_reader(std::io::Cursor::new(s)).unwrap();
+        assert_eq!(toc.saved_variables(), vec!["BagnonDB", "BagnonSets"]);
+        assert!(toc.saved_variables_per_character().is_empty());
+
+        let toc =
+            Toc::from_reader(std::io::Cursor::new("## SavedVariablesPerCharacter: A\n")).unwrap();
+        assert!(toc.saved_variables().is_empty());
+        assert_eq!(toc.saved_variables_per_character(), vec!["A"]);
     }
 }
@@ -0,0 +1,97 @@
+#[cfg(not(feature = "std"))]
+use crate::compat::prelude::*;
+
+/// Remove the client's UI escape sequences from `input`: `|cAARRGGBB` color
+/// codes, `|r` color terminators and `|T...|t` textures. Anything else,
+/// including a `|` which doesn't start one of those sequences, is kept.
+///
+/// # Example
+///
+/// ```
+/// use tocer::strip_color_codes;
+/// assert_eq!(strip_color_codes("|cff20ff20Bagnon|r"), "Bagnon");
+/// ```
+pub fn strip_color_codes(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(i) = rest.find('|') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let len = escape_len(rest);
+        if len == 0 {
+            out.push('|');
+            rest = &rest[1..];
+        } else {
+            rest = &rest[len..];
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// The paths of the `|T...|t` textures in `input`, without the size and
+/// offset fields which follow the path.
+pub(crate) fn texture_paths(input: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut rest = input;
+
+    while let Some(i) = rest.find('|') {
+        rest = &rest[i..];
+        let len = escape_len(rest);
+        if len > 0 && rest[1..].starts_with('T') {
+            let texture = &rest[2..len - 2];
+            paths.push(texture.split(':').next().unwrap_or(texture).to_string());
+        }
+        rest = &rest[len.max(1)..];
+    }
+
+    paths
+}
+
+/// The length in bytes of the escape sequence at the start of `input`, or 0
+/// if it doesn't start with one.
+fn escape_len(input: &str) -> usize {
+    let bytes = input.as_bytes();
+    match bytes.get(1) {
+        Some(b'c') if bytes.len() >= 10 && bytes[2..10].iter().all(u8::is_ascii_hexdigit) => 10,
+        Some(b'r') => 2,
+        Some(b'T') => input[2..].find("|t").map_or(0, |end| end + 4),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_color_codes() {
+        assert_eq!(strip_color_codes("|cff20ff20Bagnon|r"), "Bagnon");
+        assert_eq!(strip_color_codes("Bag|cFF00ff00non|r!"), "Bagnon!");
+        assert_eq!(
+            strip_color_codes("|TInterface\\Icons\\INV_Misc_Bag_08:16:16|t Bagnon"),
+            " Bagnon"
+        );
+    }
+
+    #[test]
+    fn test_strip_color_codes_unchanged() {
+        assert_eq!(strip_color_codes(""), "");
+        assert_eq!(strip_color_codes("Bagnon"), "Bagnon");
+        assert_eq!(strip_color_codes("a | b"), "a | b");
+        assert_eq!(strip_color_codes("|cnothex!|"), "|cnothex!|");
+        assert_eq!(strip_color_codes("|Tunterminated"), "|Tunterminated");
+    }
+
+    #[test]
+    fn test_texture_paths() {
+        assert_eq!(
+            texture_paths("|TInterface\\Icons\\INV_Misc_Bag_08:16:16|t |cff20ff20Bagnon|r"),
+            vec!["Interface\\Icons\\INV_Misc_Bag_08"]
+        );
+        assert_eq!(texture_paths("|Ta|t|Tb:0|t"), vec!["a", "b"]);
+        assert!(texture_paths("|cff20ff20Bagnon|r |Tunterminated").is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+use core::fmt;
+
+#[cfg(not(feature = "std"))]
+use crate::compat::prelude::*;
+
+/// Options controlling how `Toc::from_reader_with_options` parses its input.
+///
+/// # Example
+///
+/// ```
+/// use tocer::{ParseOptions, Toc};
+/// let options = ParseOptions {
+///     strict: true,
+///     ..Default::default()
+/// };
+/// let reader = std::io::Cursor::new("## Interface 1");
+/// assert!(Toc::from_reader_with_options(reader, options).is_err());
+/// ```
+pub struct ParseOptions {
+    /// Return `TocError::Parse` for the first malformed tag line or unsafe
+    /// file path instead of recording it in `Toc::warnings`. Other warnings,
+    /// such as for file entries which look like tags or have surrounding
+    /// whitespace, are still only recorded, since the entries are valid.
+    pub strict: bool,
+    /// Remove a trailing comment from tag values, e.g. `110002 # retail`
+    /// becomes `110002`. Only a `#` preceded by whitespace starts a comment,
+    /// since `#` can appear in values.
+    pub strip_inline_comments: bool,
+    /// Remove a trailing comment from file entries in the same way as
+    /// `strip_inline_comments` does for tag values, e.g. `Core.lua # main`
+    /// becomes `Core.lua`.
+    pub strip_file_comments: bool,
+    /// The longest line, in bytes and excluding its terminator, which will be
+    /// read before giving up with `TocError::Parse`. This stops a malicious or
+    /// corrupt file from being read into memory forever. Defaults to 64 KiB.
+    pub max_line_bytes: usize,
+    /// Lines starting with this are comments rather than file entries, as
+    /// well as those starting with `#`, which is always reserved for tags,
+    /// directives and comments. Defaults to `#`; an empty prefix is ignored.
+    pub comment_prefix: String,
+    /// Treat input without a byte order mark as UTF-16LE if most of the
+    /// first few KiB look like ASCII encoded as UTF-16LE, i.e. every other
+    /// byte is zero. Only used by `Toc::from_reader_with_encoding`.
+    pub detect_utf16: bool,
+    /// Store tag keys in lowercase, e.g. `## Interface: 1` is stored under
+    /// `interface`, so the keys don't depend on the author's casing. `tag`
+    /// ignores case either way.
+    pub lowercase_keys: bool,
+    /// Warn about file entries with whitespace around them, e.g. `c.lua  `,
+    /// which is trimmed from the stored entry either way.
+    pub warn_file_whitespace: bool,
+    /// Don't record file entries, leaving `Toc::files` empty, for when only
+    /// the tags are needed. The entries are left out of `Toc::lines` too, so
+    /// `Toc::to_writer` won't reproduce them.
+    pub skip_files: bool,
+    /// Called after each line is parsed with the total number of bytes read
+    /// so far, e.g. to update a progress bar. Input which
+    /// `Toc::from_reader_with_encoding` transcodes is counted in UTF-8.
+    pub on_progress: Option<Box<dyn FnMut(u64)>>,
+    /// Called with the text of each comment line after its leading `#` or
+    /// `comment_prefix`, e.g. to read metadata embedded in comments by addon
+    /// managers. Tags, directives and malformed tags aren't comments.
+    #[allow(clippy::type_complexity)]
+    pub on_comment: Option<Box<dyn FnMut(&str)>>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            strict: false,
+            strip_inline_comments: false,
+            strip_file_comments: false,
+            max_line_bytes: 64 * 1024,
+            comment_prefix: "#".to_string(),
+            detect_utf16: false,
+            lowercase_keys: false,
+            warn_file_whitespace: false,
+            skip_files: false,
+            on_progress: None,
+            on_comment: None,
+        }
+    }
+}
+
+impl fmt::Debug for ParseOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ParseOptions")
+            .field("strict", &self.strict)
+            .field("strip_inline_comments", &self.strip_inline_comments)
+            .field("strip_file_comments", &self.strip_file_comments)
+            .field("max_line_bytes", &self.max_line_bytes)
+            .field("comment_prefix", &self.comment_prefix)
+            .field("detect_utf16", &self.detect_utf16)
+            .field("lowercase_keys", &self.lowercase_keys)
+            .field("warn_file_whitespace", &self.warn_file_whitespace)
+            .field("skip_files", &self.skip_files)
+            .field("on_progress", &self.on_progress.as_ref().map(|_| ".."))
+            .field("on_comment", &self.on_comment.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
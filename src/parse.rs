@@ -0,0 +1,829 @@
+//! The sub-parsers used to read tag lines, for tools which need to parse
+//! strings the same way. Each returns the part of the input it couldn't
+//! parse as its error.
+
+use core::ops::Range;
+#[cfg(feature = "std")]
+use std::io::{self, BufRead, Read};
+
+#[cfg(not(feature = "std"))]
+use crate::compat::prelude::*;
+use crate::{ParseOptions, TocError, TocLine, Warning};
+
+/// Strip the `##` which starts a tag line, returning the rest of the line.
+/// Two or more `#`s are accepted, since the client tolerates `### Key: Value`.
+///
+/// # Example
+///
+/// ```
+/// use tocer::parse::parse_tag_prefix;
+/// assert_eq!(parse_tag_prefix("## Title: Foo"), Ok(" Title: Foo"));
+/// assert_eq!(parse_tag_prefix("# comment"), Err("# comment"));
+/// ```
+pub fn parse_tag_prefix(input: &str) -> Result<&str, &str> {
+    match input.strip_prefix("##") {
+        Some(rest) => Ok(rest.trim_start_matches('#')),
+        None => Err(input),
+    }
+}
+
+/// Split the trimmed key before the first `:` from the rest of the input,
+/// which still starts with the `:`.
+///
+/// # Example
+///
+/// ```
+/// use tocer::parse::parse_key;
+/// assert_eq!(parse_key(" Title : Foo"), Ok(("Title", ": Foo")));
+/// ```
+pub fn parse_key(input: &str) -> Result<(&str, &str), &str> {
+    for (i, ch) in input.char_indices() {
+        if ch == ':' {
+            let key = input[..i].trim();
+            return Ok((key, &input[i..]));
+        }
+    }
+
+    Err(input)
+}
+
+/// The trimmed value after the `:` which starts the input.
+///
+/// # Example
+///
+/// ```
+/// use tocer::parse::parse_value;
+/// assert_eq!(parse_value(": Big   Bag "), Ok("Big   Bag"));
+/// ```
+pub fn parse_value(input: &str) -> Result<&str, &str> {
+    if !input.starts_with(':') {
+        Err(input)
+    } else {
+        Ok(input[1..].trim())
+    }
+}
+
+/// Parse a whole tag line into its key and value. The key may be empty.
+///
+/// # Example
+///
+/// ```
+/// use tocer::parse::parse_key_value;
+/// assert_eq!(parse_key_value("## Foo: bar"), Ok(("Foo", "bar")));
+/// assert_eq!(parse_key_value("## bad comment"), Err(" bad comment"));
+/// ```
+pub fn parse_key_value(input: &str) -> Result<(&str, &str), &str> {
+    let (k, input) = parse_key(parse_tag_prefix(input)?)?;
+    let v = parse_value(input)?;
+    Ok((k, v))
+}
+
+/// Remove a trailing `# ...` comment which is preceded by whitespace.
+fn strip_inline_comment(input: &str) -> &str {
+    input
+        .char_indices()
+        .zip(input.chars().skip(1))
+        .find(|((_, ch), next)| ch.is_whitespace() && *next == '#')
+        .map_or(input, |((i, _), _)| input[..i].trim_end())
+}
+
+fn directive(input: &str) -> Result<&str, &str> {
+    match input
+        .trim()
+        .strip_prefix("#@")
+        .and_then(|s| s.strip_suffix('@'))
+    {
+        Some(name) if !name.is_empty() => Ok(name),
+        _ => Err(input),
+    }
+}
+
+/// Whether a line which isn't a tag starts with `#` or `comment_prefix`,
+/// making it a comment or directive rather than a file entry.
+fn is_comment(input: &str, comment_prefix: &str) -> bool {
+    input.starts_with('#') || (!comment_prefix.is_empty() && input.starts_with(comment_prefix))
+}
+
+fn file_path<'a>(input: &'a str, comment_prefix: &str) -> Result<&'a str, &'a str> {
+    if is_comment(input, comment_prefix) {
+        Err(input)
+    } else {
+        Ok(input.trim())
+    }
+}
+
+/// Why a file entry shouldn't be trusted, if it's absolute or uses `..` to
+/// refer to something outside of the addon's folder.
+fn unsafe_path(path: &str) -> Option<&'static str> {
+    let bytes = path.as_bytes();
+    let has_drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+    if path.starts_with(['/', '\\']) || has_drive {
+        Some("file path is absolute")
+    } else if path.split(['/', '\\']).any(|part| part == "..") {
+        Some("file path refers outside of the addon folder")
+    } else {
+        None
+    }
+}
+
+/// Whether a file entry such as `Version: 1.0` looks like a tag line which is
+/// missing its `##`. A single letter before the `:` is a drive letter.
+fn looks_like_tag(path: &str) -> bool {
+    parse_key(path).is_ok_and(|(key, _)| {
+        key.len() > 1
+            && key
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    })
+}
+
+/// A parsed line, with its 1-based line number, its byte range in the input,
+/// the innermost `#@...@` block it's in and any warnings about it.
+pub(crate) struct ParsedLine {
+    pub(crate) number: usize,
+    pub(crate) span: Range<usize>,
+    pub(crate) line: TocLine,
+    pub(crate) condition: Option<String>,
+    pub(crate) warnings: Vec<Warning>,
+}
+
+/// Parses lines one at a time, keeping track of the state which spans lines.
+pub(crate) struct Parser {
+    options: ParseOptions,
+    line_number: usize,
+    /// The number of bytes of input before the next line.
+    bytes_read: usize,
+    /// The packager blocks, such as `#@retail@`, which haven't been closed.
+    conditions: Vec<String>,
+}
+
+impl Parser {
+    pub(crate) fn new(options: ParseOptions) -> Parser {
+        Parser {
+            options,
+            line_number: 0,
+            bytes_read: 0,
+            conditions: Vec::new(),
+        }
+    }
+
+    /// Open or close a packager block for the directive `name`, returning
+    /// the block enclosing the directive itself. Closing a block which isn't
+    /// open is ignored.
+    fn update_conditions(&mut self, name: &str) -> Option<String> {
+        match name.strip_prefix("end-") {
+            Some(end) => {
+                if let Some(i) = self.conditions.iter().rposition(|c| c == end) {
+                    self.conditions.truncate(i);
+                }
+                self.conditions.last().cloned()
+            }
+            None => {
+                let enclosing = self.conditions.last().cloned();
+                self.conditions.push(name.to_string());
+                enclosing
+            }
+        }
+    }
+
+    /// Parse the next line of the input, which may still end with its line
+    /// terminator.
+    pub(crate) fn parse(&mut self, line: &str) -> Result<ParsedLine, TocError> {
+        self.line_number += 1;
+        let text = if self.line_number == 1 {
+            line.strip_prefix('\u{feff}').unwrap_or(line)
+        } else {
+            line
+        };
+        let start = self.bytes_read + line.len() - text.len();
+        self.bytes_read += line.len();
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        let text = text.strip_suffix('\r').unwrap_or(text);
+        let span = start..start + text.len();
+        let mut warnings = Vec::new();
+        let mut condition = self.conditions.last().cloned();
+
+        let line = if let Ok((k, v)) = parse_key_value(text) {
+            if k.is_empty() {
+                warnings.push(self.warning(text.trim_end(), "tag line has an empty key")?);
+                TocLine::MalformedTag(text.to_string())
+            } else {
+                let v = if self.options.strip_inline_comments {
+                    strip_inline_comment(v)
+                } else {
+                    v
+                };
+                let k = if self.options.lowercase_keys {
+                    k.to_lowercase()
+                } else {
+                    k.to_string()
+                };
+                TocLine::Tag {
+                    key: k,
+                    value: v.to_string(),
+                    text: text.to_string(),
+                }
+            }
+        } else if parse_tag_prefix(text).is_ok() {
+            warnings.push(self.warning(text.trim_end(), "tag line missing ':'")?);
+            TocLine::MalformedTag(text.to_string())
+        } else if text.trim().is_empty() {
+            TocLine::Blank
+        } else if self.options.skip_files && !is_comment(text, &self.options.comment_prefix) {
+            // `Lines` drops file entries when skipping them, so don't spend
+            // any time on this one.
+            TocLine::File {
+                path: String::new(),
+                text: String::new(),
+            }
+        } else if let Ok(path) = file_path(text, &self.options.comment_prefix) {
+            warnings.extend(self.file_warnings(text, path)?);
+            let path = if self.options.strip_file_comments {
+                strip_inline_comment(path)
+            } else {
+                path
+            };
+            TocLine::File {
+                path: path.to_string(),
+                text: text.to_string(),
+            }
+        } else if let Ok(name) = directive(text) {
+            condition = self.update_conditions(name);
+            TocLine::Directive {
+                name: name.to_string(),
+                text: text.to_string(),
+            }
+        } else {
+            if let Some(on_comment) = &mut self.options.on_comment {
+                let prefix = &self.options.comment_prefix;
+                let body = text
+                    .strip_prefix('#')
+                    .or_else(|| text.strip_prefix(prefix))
+                    .unwrap_or(text);
+                on_comment(body);
+            }
+            TocLine::Comment(text.to_string())
+        };
+
+        Ok(ParsedLine {
+            number: self.line_number,
+            span,
+            line,
+            condition,
+            warnings,
+        })
+    }
+
+    /// Parse the next line from its raw bytes, checking its length before
+    /// treating it as UTF-8 so that an over-long line which was cut short is
+    /// reported as too long.
+    pub(crate) fn parse_bytes(&mut self, line: &[u8]) -> Result<ParsedLine, TocError> {
+        let content = line.strip_suffix(b"\n").unwrap_or(line);
+        let content = content.strip_suffix(b"\r").unwrap_or(content);
+        if content.len() > self.options.max_line_bytes {
+            return Err(TocError::Parse {
+                line: self.line_number + 1,
+                reason: format!("line is longer than {} bytes", self.options.max_line_bytes),
+                text: String::from_utf8_lossy(&content[..content.len().min(32)]).into_owned(),
+            });
+        }
+
+        let text = core::str::from_utf8(line).map_err(|_| TocError::Parse {
+            line: self.line_number + 1,
+            reason: "line is not valid UTF-8".to_string(),
+            text: String::from_utf8_lossy(content).into_owned(),
+        })?;
+        let parsed = self.parse(text)?;
+        if let Some(on_progress) = &mut self.options.on_progress {
+            on_progress(self.bytes_read as u64);
+        }
+        Ok(parsed)
+    }
+
+    /// Wrap an error from reading the input with the number of the last line
+    /// read.
+    #[cfg(feature = "std")]
+    pub(crate) fn io_error(&self, source: io::Error) -> TocError {
+        TocError::Io {
+            line: Some(self.line_number).filter(|&line| line > 0),
+            source,
+        }
+    }
+
+    /// The most bytes to read for one line: enough for the longest allowed
+    /// line, its terminator, and one more byte to tell that it's too long.
+    #[cfg(feature = "std")]
+    pub(crate) fn read_limit(&self) -> u64 {
+        self.options.max_line_bytes.saturating_add(3) as u64
+    }
+
+    /// The warnings about a file entry, or the error for an unsafe path in
+    /// strict mode. The other warnings are advisory, since the entry is
+    /// still a valid file path.
+    fn file_warnings(&self, text: &str, path: &str) -> Result<Vec<Warning>, TocError> {
+        let mut warnings = Vec::new();
+        if let Some(reason) = unsafe_path(path) {
+            warnings.push(self.warning(text.trim_end(), reason)?);
+        }
+        if looks_like_tag(path) {
+            warnings.push(self.advisory(
+                text.trim_end(),
+                "file entry looks like a tag missing its '##'",
+            ));
+        }
+        if self.options.warn_file_whitespace && path.len() != text.len() {
+            warnings.push(self.advisory(text, "file entry has surrounding whitespace"));
+        }
+        Ok(warnings)
+    }
+
+    /// The warning for a problem with a line, or the error in strict mode.
+    fn warning(&self, text: &str, reason: &str) -> Result<Warning, TocError> {
+        if self.options.strict {
+            return Err(TocError::Parse {
+                line: self.line_number,
+                reason: reason.to_string(),
+                text: text.to_string(),
+            });
+        }
+
+        Ok(self.advisory(text, reason))
+    }
+
+    /// The warning for a problem with a line, even in strict mode.
+    fn advisory(&self, text: &str, reason: &str) -> Warning {
+        Warning {
+            line: self.line_number,
+            reason: reason.to_string(),
+            text: text.to_string(),
+        }
+    }
+}
+
+/// An iterator which lazily reads and parses one line at a time. It stops
+/// after the first error.
+#[cfg(feature = "std")]
+pub(crate) struct Lines<R> {
+    reader: R,
+    buf: Vec<u8>,
+    parser: Parser,
+    done: bool,
+}
+
+#[cfg(feature = "std")]
+impl<R: BufRead> Lines<R> {
+    pub(crate) fn new(reader: R, options: ParseOptions) -> Lines<R> {
+        Lines {
+            reader,
+            buf: Vec::new(),
+            parser: Parser::new(options),
+            done: false,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: BufRead> Iterator for Lines<R> {
+    type Item = Result<ParsedLine, TocError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.done {
+                return None;
+            }
+
+            self.buf.clear();
+            let limit = self.parser.read_limit();
+            let result = match (&mut self.reader)
+                .take(limit)
+                .read_until(b'\n', &mut self.buf)
+            {
+                Ok(0) => {
+                    self.done = true;
+                    return None;
+                }
+                Ok(_) => self.parser.parse_bytes(&self.buf),
+                Err(e) => Err(self.parser.io_error(e)),
+            };
+            self.done = result.is_err();
+
+            let skip = self.parser.options.skip_files;
+            if !matches!(&result, Ok(ParsedLine { line: TocLine::File { .. }, .. }) if skip) {
+                return Some(result);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_begin() {
+        assert_eq!(parse_tag_prefix(""), Err(""));
+        assert_eq!(parse_tag_prefix("#"), Err("#"));
+        assert_eq!(parse_tag_prefix("##"), Ok(""));
+        assert_eq!(parse_tag_prefix("### a"), Ok(" a"));
+        assert_eq!(parse_tag_prefix("# comment"), Err("# comment"));
+    }
+
+    #[test]
+    fn test_key_value_pair_hashes() {
+        assert_eq!(parse_key_value("## Interface: 1"), Ok(("Interface", "1")));
+        assert_eq!(parse_key_value("### Interface: 1"), Ok(("Interface", "1")));
+        assert_eq!(parse_key_value("# comment: x"), Err("# comment: x"));
+        assert_eq!(parse_key_value("### comment"), Err(" comment"));
+    }
+
+    #[test]
+    fn test_directive() {
+        assert_eq!(directive("#@no-lib-strip@"), Ok("no-lib-strip"));
+        assert_eq!(directive(" #@end-retail@ "), Ok("end-retail"));
+        assert_eq!(directive("#@@"), Err("#@@"));
+        assert_eq!(directive("#@"), Err("#@"));
+        assert_eq!(directive("# @retail@"), Err("# @retail@"));
+        assert_eq!(directive("#@retail"), Err("#@retail"));
+    }
+
+    #[test]
+    fn test_key() {
+        assert_eq!(parse_key(""), Err(""));
+        assert_eq!(parse_key("a"), Err("a"));
+        assert_eq!(parse_key(" a "), Err(" a "));
+        assert_eq!(parse_key("a:"), Ok(("a", ":")));
+        assert_eq!(parse_key(" a:"), Ok(("a", ":")));
+        assert_eq!(parse_key("a:"), Ok(("a", ":")));
+        assert_eq!(parse_key("a :"), Ok(("a", ":")));
+        assert_eq!(parse_key(" a :"), Ok(("a", ":")));
+    }
+
+    #[test]
+    fn test_value() {
+        assert_eq!(parse_value(""), Err(""));
+        assert_eq!(parse_value(" "), Err(" "));
+        assert_eq!(parse_value(": "), Ok(""));
+        assert_eq!(parse_value(":A"), Ok("A"));
+        assert_eq!(parse_value(": A"), Ok("A"));
+        assert_eq!(parse_value(": A "), Ok("A"));
+    }
+
+    #[test]
+    fn test_value_keeps_internal_whitespace() {
+        assert_eq!(parse_value(":  Big   Bag  "), Ok("Big   Bag"));
+        assert_eq!(parse_value(":\tBig \t Bag\t"), Ok("Big \t Bag"));
+        assert_eq!(
+            parse_key_value("## Title: Big   Bag"),
+            Ok(("Title", "Big   Bag"))
+        );
+    }
+
+    #[test]
+    fn test_key_value_pair_with_colons_in_value() {
+        assert_eq!(
+            parse_key_value("## Notes: See http://example.com:8080"),
+            Ok(("Notes", "See http://example.com:8080"))
+        );
+        assert_eq!(parse_key_value("##a::b:"), Ok(("a", ":b:")));
+    }
+
+    #[test]
+    fn test_parse_key_value() {
+        assert_eq!(parse_key_value("## Foo: bar"), Ok(("Foo", "bar")));
+        assert_eq!(parse_key_value("##Foo:bar"), Ok(("Foo", "bar")));
+        assert_eq!(parse_key_value("Foo: bar"), Err("Foo: bar"));
+    }
+
+    #[test]
+    fn test_key_value_pair_with_tabs() {
+        assert_eq!(parse_key_value("##\tTitle\t:\tFoo"), Ok(("Title", "Foo")));
+        assert_eq!(parse_key_value("##Title:\tFoo\t\t"), Ok(("Title", "Foo")));
+        assert_eq!(
+            parse_key_value("## \t OptionalDeps \t: \tBagBrother,\tWoWUnit \t"),
+            Ok(("OptionalDeps", "BagBrother,\tWoWUnit"))
+        );
+        assert_eq!(parse_key("\tTitle\t:"), Ok(("Title", ":")));
+        assert_eq!(parse_value(":\t\t"), Ok(""));
+    }
+
+    #[test]
+    fn test_strip_inline_comment() {
+        assert_eq!(strip_inline_comment("110002 # retail"), "110002");
+        assert_eq!(strip_inline_comment("110002\t#retail # x"), "110002");
+        assert_eq!(strip_inline_comment("Foo#1"), "Foo#1");
+        assert_eq!(strip_inline_comment("#1"), "#1");
+        assert_eq!(strip_inline_comment(""), "");
+    }
+
+    #[test]
+    fn test_file_path() {
+        assert_eq!(file_path(" a.lua ", "#"), Ok("a.lua"));
+        assert_eq!(file_path("# a.lua", "#"), Err("# a.lua"));
+        assert_eq!(file_path("; a.lua", ";"), Err("; a.lua"));
+        assert_eq!(file_path("#@retail@", ";"), Err("#@retail@"));
+        assert_eq!(file_path("a.lua", ""), Ok("a.lua"));
+    }
+
+    #[test]
+    fn test_lines_on_progress() {
+        let progress = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let sink = progress.clone();
+        let options = ParseOptions {
+            on_progress: Some(Box::new(move |bytes| sink.borrow_mut().push(bytes))),
+            ..Default::default()
+        };
+        let input = "## Title: Foo\r\n\na.lua";
+        assert_eq!(Lines::new(input.as_bytes(), options).count(), 3);
+        assert_eq!(*progress.borrow(), vec![15, 16, input.len() as u64]);
+    }
+
+    #[test]
+    fn test_parser_file_whitespace() {
+        let mut parser = Parser::new(ParseOptions::default());
+        assert!(parser.parse("c.lua  \n").unwrap().warnings.is_empty());
+
+        let mut parser = Parser::new(ParseOptions {
+            warn_file_whitespace: true,
+            ..Default::default()
+        });
+        let parsed = parser.parse("c.lua  \r\n").unwrap();
+        assert_eq!(
+            parsed.line,
+            TocLine::File {
+                path: "c.lua".to_string(),
+                text: "c.lua  ".to_string(),
+            }
+        );
+        assert_eq!(parsed.warnings.len(), 1);
+        assert_eq!(
+            parsed.warnings[0].reason,
+            "file entry has surrounding whitespace"
+        );
+        assert_eq!(parsed.warnings[0].text, "c.lua  ");
+        assert_eq!(parser.parse(" d.lua").unwrap().warnings.len(), 1);
+        assert!(parser.parse("e.lua\n").unwrap().warnings.is_empty());
+
+        let mut parser = Parser::new(ParseOptions {
+            strict: true,
+            warn_file_whitespace: true,
+            ..Default::default()
+        });
+        assert_eq!(parser.parse("c.lua  \n").unwrap().warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_parser_on_comment() {
+        let comments = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let sink = comments.clone();
+        let mut parser = Parser::new(ParseOptions {
+            on_comment: Some(Box::new(move |body| {
+                sink.borrow_mut().push(body.to_string())
+            })),
+            ..Default::default()
+        });
+        for line in &[
+            "# {\"id\": 1}\n",
+            "## Title: Foo\n",
+            "#@retail@\n",
+            "#x\n",
+            "a.lua\n",
+        ] {
+            parser.parse(line).unwrap();
+        }
+        assert_eq!(*comments.borrow(), vec![" {\"id\": 1}", "x"]);
+    }
+
+    #[test]
+    fn test_lines_io_error_line() {
+        struct FailingReader(&'static [u8]);
+
+        impl Read for FailingReader {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.0.is_empty() {
+                    return Err(io::Error::other("reset"));
+                }
+                let n = self.0.len().min(buf.len());
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+
+        let reader = io::BufReader::new(FailingReader(b"## Title: Foo\na.lua\n"));
+        let results: Vec<_> = Lines::new(reader, ParseOptions::default()).collect();
+        assert_eq!(results.len(), 3);
+        match &results[2] {
+            Err(TocError::Io { line, source }) => {
+                assert_eq!(*line, Some(2));
+                assert_eq!(source.to_string(), "reset");
+            }
+            _ => panic!("expected an I/O error"),
+        }
+
+        let reader = io::BufReader::new(FailingReader(b""));
+        match Lines::new(reader, ParseOptions::default()).next() {
+            Some(Err(TocError::Io { line: None, .. })) => {}
+            _ => panic!("expected an I/O error without a line"),
+        }
+    }
+
+    #[test]
+    fn test_parser_strip_file_comments() {
+        let mut parser = Parser::new(ParseOptions::default());
+        let parsed = parser.parse("Core.lua # main file\n").unwrap();
+        assert!(
+            matches!(parsed.line, TocLine::File { path, .. } if path == "Core.lua # main file")
+        );
+
+        let mut parser = Parser::new(ParseOptions {
+            strip_file_comments: true,
+            ..Default::default()
+        });
+        let parsed = parser.parse("Core.lua # main file\n").unwrap();
+        assert_eq!(
+            parsed.line,
+            TocLine::File {
+                path: "Core.lua".to_string(),
+                text: "Core.lua # main file".to_string(),
+            }
+        );
+        let parsed = parser.parse("Lib#1.lua\n").unwrap();
+        assert!(matches!(parsed.line, TocLine::File { path, .. } if path == "Lib#1.lua"));
+    }
+
+    #[test]
+    fn test_lines_skip_files() {
+        let options = ParseOptions {
+            skip_files: true,
+            ..Default::default()
+        };
+        let input: &[u8] = b"## Title: Foo\n../a.lua\n# comment\nb.lua\n";
+        let lines: Vec<_> = Lines::new(input, options).map(Result::unwrap).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].number, 3);
+        assert!(lines.iter().all(|parsed| parsed.warnings.is_empty()));
+
+        let mut parser = Parser::new(ParseOptions {
+            skip_files: true,
+            ..Default::default()
+        });
+        let parsed = parser.parse("../a.lua\n").unwrap();
+        assert_eq!(
+            parsed.line,
+            TocLine::File {
+                path: String::new(),
+                text: String::new(),
+            }
+        );
+        assert!(parsed.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_parser_conditions() {
+        let mut parser = Parser::new(ParseOptions::default());
+        let conditions: Vec<_> = [
+            "a.lua\n",
+            "#@no-lib-strip@\n",
+            "libs\\LibStub.lua\n",
+            "#@retail@\n",
+            "retail.lua\n",
+            "#@end-retail@\n",
+            "#@end-no-lib-strip@\n",
+            "#@end-debug@\n",
+            "b.lua\n",
+        ]
+        .iter()
+        .map(|line| parser.parse(line).unwrap().condition)
+        .collect();
+        let no_lib_strip = Some("no-lib-strip".to_string());
+        assert_eq!(
+            conditions,
+            vec![
+                None,
+                None,
+                no_lib_strip.clone(),
+                no_lib_strip.clone(),
+                Some("retail".to_string()),
+                no_lib_strip,
+                None,
+                None,
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_comment_prefix() {
+        let mut parser = Parser::new(ParseOptions {
+            comment_prefix: ";".to_string(),
+            ..Default::default()
+        });
+        let lines: Vec<_> = ["; generated\n", "## Title: Foo\n", "a.lua\n", "# b.lua\n"]
+            .iter()
+            .map(|line| parser.parse(line).unwrap().line)
+            .collect();
+        assert_eq!(lines[0], TocLine::Comment("; generated".to_string()));
+        assert!(matches!(lines[1], TocLine::Tag { .. }));
+        assert!(matches!(lines[2], TocLine::File { .. }));
+        assert_eq!(lines[3], TocLine::Comment("# b.lua".to_string()));
+    }
+
+    #[test]
+    fn test_looks_like_tag() {
+        assert!(looks_like_tag("Version: 1.0"));
+        assert!(looks_like_tag("X-Website:https://example.com"));
+        assert!(!looks_like_tag("C:\\evil.lua"));
+        assert!(!looks_like_tag("dir\\a:b.lua"));
+        assert!(!looks_like_tag("a.lua"));
+    }
+
+    #[test]
+    fn test_unsafe_path() {
+        assert_eq!(unsafe_path("C:\\evil"), Some("file path is absolute"));
+        assert_eq!(unsafe_path("/abs/path"), Some("file path is absolute"));
+        assert_eq!(unsafe_path("\\abs\\path"), Some("file path is absolute"));
+        assert_eq!(
+            unsafe_path("../escape"),
+            Some("file path refers outside of the addon folder")
+        );
+        assert_eq!(
+            unsafe_path("sub\\..\\..\\escape"),
+            Some("file path refers outside of the addon folder")
+        );
+        assert_eq!(unsafe_path("sub/dir/ok.lua"), None);
+        assert_eq!(unsafe_path("sub\\dir..\\ok.lua"), None);
+    }
+
+    #[test]
+    fn test_parser_unsafe_path() {
+        let mut parser = Parser::new(ParseOptions::default());
+        let parsed = parser.parse("../escape.lua\n").unwrap();
+        assert!(matches!(parsed.line, TocLine::File { .. }));
+        assert_eq!(parsed.warnings.len(), 1);
+        assert!(parser.parse("ok.lua\n").unwrap().warnings.is_empty());
+
+        let mut parser = Parser::new(ParseOptions {
+            strict: true,
+            ..Default::default()
+        });
+        assert!(parser.parse("C:\\evil.lua\n").is_err());
+    }
+
+    #[test]
+    fn test_parser() {
+        let mut parser = Parser::new(ParseOptions::default());
+        let first = parser.parse("\u{feff}## Title: Foo\r\n").unwrap();
+        assert_eq!(first.number, 1);
+        assert_eq!(
+            first.line,
+            TocLine::Tag {
+                key: "Title".to_string(),
+                value: "Foo".to_string(),
+                text: "## Title: Foo".to_string(),
+            }
+        );
+
+        assert_eq!(first.span, 3..16);
+
+        let second = parser.parse("## bad\n").unwrap();
+        assert_eq!(second.number, 2);
+        assert_eq!(second.span, 18..24);
+        assert_eq!(second.warnings.len(), 1);
+        assert_eq!(second.warnings[0].line, 2);
+        assert_eq!(second.warnings[0].text, "## bad");
+    }
+
+    #[test]
+    fn test_parser_empty_key() {
+        let mut parser = Parser::new(ParseOptions::default());
+        let parsed = parser.parse("## : x\n").unwrap();
+        assert_eq!(parsed.line, TocLine::MalformedTag("## : x".to_string()));
+        assert_eq!(parsed.warnings[0].reason, "tag line has an empty key");
+
+        let mut parser = Parser::new(ParseOptions {
+            strict: true,
+            ..Default::default()
+        });
+        assert!(parser.parse("## : x\n").is_err());
+    }
+
+    #[test]
+    fn test_lines_max_line_bytes() {
+        let options = ParseOptions {
+            max_line_bytes: 8,
+            ..Default::default()
+        };
+        let input: &[u8] = b"## A: 12\r\n## Title: much too long\n";
+        let mut lines = Lines::new(input, options);
+        assert!(lines.next().unwrap().is_ok());
+        match lines.next() {
+            Some(Err(TocError::Parse { line, reason, text })) => {
+                assert_eq!(line, 2);
+                assert_eq!(reason, "line is longer than 8 bytes");
+                assert_eq!(text, "## Title: m");
+            }
+            _ => panic!("expected a parse error"),
+        }
+        assert!(lines.next().is_none());
+    }
+}
@@ -0,0 +1,117 @@
+use core::convert::TryFrom;
+use core::fmt;
+
+/// The expansion names for each major version. Add new expansions here.
+const EXPANSIONS: &[(u16, &str)] = &[
+    (1, "Classic"),
+    (2, "The Burning Crusade"),
+    (3, "Wrath of the Lich King"),
+    (4, "Cataclysm"),
+    (5, "Mists of Pandaria"),
+    (6, "Warlords of Draenor"),
+    (7, "Legion"),
+    (8, "Battle for Azeroth"),
+    (9, "Shadowlands"),
+    (10, "Dragonflight"),
+    (11, "The War Within"),
+    (12, "Midnight"),
+];
+
+/// A game client version as encoded in the `Interface` tag, where `110002`
+/// means 11.0.2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InterfaceVersion {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+}
+
+impl InterfaceVersion {
+    /// Parse an interface number such as `110002`. Leading and trailing
+    /// whitespace is ignored, anything other than digits is rejected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::InterfaceVersion;
+    /// let version = InterfaceVersion::parse("110002").unwrap();
+    /// assert_eq!(version.to_string(), "11.0.2");
+    /// ```
+    pub fn parse(input: &str) -> Option<InterfaceVersion> {
+        let input = input.trim();
+        if input.is_empty() || !input.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        let n: u32 = input.parse().ok()?;
+        Some(InterfaceVersion {
+            major: u16::try_from(n / 10000).ok()?,
+            minor: ((n / 100) % 100) as u16,
+            patch: (n % 100) as u16,
+        })
+    }
+
+    /// The name of the expansion this version belongs to, or `None` if it's
+    /// not one this crate knows about.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tocer::InterfaceVersion;
+    /// let version = InterfaceVersion::parse("100200").unwrap();
+    /// assert_eq!(version.expansion(), Some("Dragonflight"));
+    /// ```
+    pub fn expansion(&self) -> Option<&'static str> {
+        EXPANSIONS
+            .iter()
+            .find(|(major, _)| *major == self.major)
+            .map(|(_, name)| *name)
+    }
+}
+
+impl fmt::Display for InterfaceVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let v = InterfaceVersion::parse("11302").unwrap();
+        assert_eq!((v.major, v.minor, v.patch), (1, 13, 2));
+        let v = InterfaceVersion::parse(" 110002 ").unwrap();
+        assert_eq!((v.major, v.minor, v.patch), (11, 0, 2));
+        assert_eq!(InterfaceVersion::parse(""), None);
+        assert_eq!(InterfaceVersion::parse("11.0.2"), None);
+        assert_eq!(InterfaceVersion::parse("+110002"), None);
+        assert_eq!(InterfaceVersion::parse("abc"), None);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            InterfaceVersion::parse("11302").unwrap().to_string(),
+            "1.13.2"
+        );
+        assert_eq!(
+            InterfaceVersion::parse("110002").unwrap().to_string(),
+            "11.0.2"
+        );
+    }
+
+    #[test]
+    fn test_expansion() {
+        let expansion = |s| InterfaceVersion::parse(s).unwrap().expansion();
+        assert_eq!(expansion("11302"), Some("Classic"));
+        assert_eq!(expansion("20504"), Some("The Burning Crusade"));
+        assert_eq!(expansion("100200"), Some("Dragonflight"));
+        assert_eq!(expansion("110002"), Some("The War Within"));
+        assert_eq!(expansion("990000"), None);
+        assert_eq!(expansion("0"), None);
+    }
+}